@@ -0,0 +1,132 @@
+//! generated corpora for the benchmarks, deterministic so runs compare
+//!
+//! every corpus holds distinct keys in a fixed order which is not the sorted one.
+//! shared by the benchmarks, each of them uses a part of it
+#![allow(dead_code)]
+use std::collections::HashSet;
+
+/// xorshift
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new() -> Self {
+        Rng(0x2545f4914f6cdd1d)
+    }
+
+    pub fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    pub fn pick<'a>(&mut self, items: &[&'a str]) -> &'a str {
+        items[(self.next() % items.len() as u64) as usize]
+    }
+
+    /// a made up word of 1 to `max` syllables, so words share prefixes like real ones do
+    pub fn word(&mut self, max: u64) -> String {
+        let syllables = 1 + self.next() % max;
+        (0..syllables).map(|_| self.pick(&SYLLABLES)).collect()
+    }
+}
+
+const SYLLABLES: [&str; 48] = [
+    "ro", "ma", "nus", "mu", "lus", "ru", "ben", "ber", "bi", "con", "cun", "dus", "a", "e", "i", "o",
+    "ta", "te", "ti", "to", "ka", "ke", "ki", "ko", "sa", "se", "si", "so", "na", "ne", "ni", "no",
+    "ing", "er", "ed", "ly", "tion", "ment", "ness", "able", "st", "tr", "pl", "gr", "ch", "sh", "th", "qu",
+];
+
+const HOSTS: [&str; 8] = [
+    "https://www.example.com", "https://docs.rs", "https://crates.io", "http://localhost:8080",
+    "https://en.wikipedia.org", "https://github.com", "https://news.ycombinator.com", "https://api.example.org",
+];
+
+const EXTENSIONS: [&str; 8] = ["rs", "toml", "md", "json", "txt", "png", "lock", "html"];
+
+const EMOJI: [&str; 24] = [
+    "😀", "😂", "🥲", "😍", "🤡", "👍", "👍🏽", "🙏", "🔥", "✨", "🎉", "❤️", "💔", "🚀", "🌍", "🍕",
+    "👨‍👩‍👧", "🏳️‍🌈", "🇮🇱", "🇯🇵", "⭐", "🐍", "🦀", " ",
+];
+
+/// the corpora the benchmarks run over
+pub const KINDS: [&str; 4] = ["words", "urls", "paths", "emoji"];
+
+pub fn generate(kind: &str, n: usize) -> Vec<String> {
+    match kind {
+        "words" => words(n),
+        "urls" => urls(n),
+        "paths" => paths(n),
+        "emoji" => emoji(n),
+        _ => panic!("unknown corpus {kind}"),
+    }
+}
+
+/// collects `n` distinct keys made up by `make` and scrambles their order
+fn distinct(n: usize, mut make: impl FnMut(&mut Rng) -> String) -> Vec<String> {
+    let mut rng = Rng::new();
+    let mut keys = HashSet::with_capacity(n);
+    while keys.len() < n {
+        keys.insert(make(&mut rng));
+    }
+    let mut keys = keys.into_iter().collect::<Vec<_>>();
+    // a fixed insertion order which is not the sorted one
+    keys.sort_by_key(|key| key.bytes().fold(0_u64, |hash, b| (hash ^ b as u64).wrapping_mul(0x100000001b3)));
+    keys
+}
+
+/// english-like words
+pub fn words(n: usize) -> Vec<String> {
+    distinct(n, |rng| rng.word(5))
+}
+
+/// urls sharing a few hosts, with paths and sometimes a query
+pub fn urls(n: usize) -> Vec<String> {
+    distinct(n, |rng| {
+        let mut url = rng.pick(&HOSTS).to_string();
+        for _ in 0..1 + rng.next() % 4 {
+            url.push('/');
+            url.push_str(&rng.word(3));
+        }
+        if rng.next() % 3 == 0 {
+            url.push_str("?q=");
+            url.push_str(&rng.word(2));
+        }
+        url
+    })
+}
+
+/// deep file paths under a few roots
+pub fn paths(n: usize) -> Vec<String> {
+    distinct(n, |rng| {
+        let mut path = format!("/home/{}/src", rng.pick(&["avner", "alex", "sam", "kim"]));
+        for _ in 0..1 + rng.next() % 6 {
+            path.push('/');
+            path.push_str(&rng.word(2));
+        }
+        path.push('.');
+        path.push_str(rng.pick(&EXTENSIONS));
+        path
+    })
+}
+
+/// emoji sequences (with skin tones, flags and ZWJ sequences) mixed with a few words
+pub fn emoji(n: usize) -> Vec<String> {
+    distinct(n, |rng| {
+        let mut key = String::new();
+        for _ in 0..2 + rng.next() % 6 {
+            if rng.next() % 4 == 0 {
+                key.push_str(&rng.word(1));
+            } else {
+                key.push_str(rng.pick(&EMOJI));
+            }
+        }
+        key
+    })
+}
+
+/// the first chars of every `step`th key, the prefixes the prefix queries run with
+pub fn prefixes(keys: &[String], count: usize, chars: usize) -> Vec<String> {
+    let step = (keys.len() / count).max(1);
+    keys.iter().step_by(step).take(count).map(|key| key.chars().take(chars).collect()).collect()
+}
@@ -0,0 +1,158 @@
+//! builds a trie from a dictionary of 1M words and reports the time of the main operations
+//! along with the heap held by the trie
+//!
+//! `cargo bench --bench dictionary`, set `DICTIONARY` to a file with one word per line to use a real one,
+//! otherwise 1M distinct words are made up from syllables (so they share prefixes like real words do).
+//! a second trie holds 1000 prefixes of a 10k char text, every one a node below the previous one.
+//! `benches/layout_baseline.sh` runs the same bench against the tree with a `HashMap` of children per node
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::hint::black_box;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+use ab_radix_trie::Trie;
+
+mod corpus;
+
+/// counts the bytes currently allocated
+struct Counting;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATED.fetch_add(new_size, Ordering::Relaxed);
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+const WORDS: usize = 1_000_000;
+
+fn dictionary() -> Vec<String> {
+    if let Ok(path) = std::env::var("DICTIONARY") {
+        let text = std::fs::read_to_string(path).expect("reading the dictionary");
+        return text.lines().filter(|line| !line.is_empty()).map(str::to_string).collect()
+    }
+    corpus::words(WORDS)
+}
+
+/// the word without its last char, cut at a char boundary so multibyte words work
+fn without_last_char(word: &str) -> &str {
+    word.char_indices().last().map_or(word, |(i, _)| &word[..i])
+}
+
+/// at most the first `n` chars of the word
+fn first_chars(word: &str, n: usize) -> &str {
+    word.char_indices().nth(n).map_or(word, |(i, _)| &word[..i])
+}
+
+fn timed<T>(name: &str, f: impl FnOnce() -> T) -> T {
+    let start = Instant::now();
+    let result = f();
+    println!("{name:<24} {:>10.1?}", start.elapsed());
+    result
+}
+
+fn main() {
+    let words = dictionary();
+    println!("{} words", words.len());
+
+    let before = ALLOCATED.load(Ordering::Relaxed);
+    let trie = timed("insert", || {
+        let mut trie: Trie<u32> = Trie::new();
+        for (i, word) in words.iter().enumerate() {
+            trie.insert(word, Some(i as u32));
+        }
+        trie
+    });
+    let heap = ALLOCATED.load(Ordering::Relaxed) - before;
+    let stats = trie.stats();
+    println!("{:<24} {:>10.1} MiB ({} nodes, {:.1} bytes per node)", "heap", heap as f64 / (1 << 20) as f64,
+             stats.node_count, heap as f64 / stats.node_count as f64);
+
+    timed("get (all words)", || {
+        for word in &words {
+            black_box(trie.get(word));
+        }
+    });
+    timed("get (missing words)", || {
+        for word in &words {
+            black_box(trie.get(without_last_char(word)));
+        }
+    });
+    timed("iter (sorted keys)", || black_box(trie.keys().count()));
+    timed("iter_prefix (400 x)", || {
+        for word in words.iter().take(400) {
+            black_box(trie.iter_prefix(first_chars(word, 3)).count());
+        }
+    });
+    timed("get_suffixes_values", || {
+        for word in words.iter().take(400) {
+            black_box(trie.get_suffixes_values(first_chars(word, 3)));
+        }
+    });
+
+    // the frozen trie came later than the layout the baseline run (see layout_baseline.sh) measures
+    #[cfg(not(layout_baseline))]
+    {
+        let before = ALLOCATED.load(Ordering::Relaxed);
+        let frozen = timed("freeze", || trie.clone().freeze());
+        let heap = ALLOCATED.load(Ordering::Relaxed) - before;
+        println!("{:<24} {:>10.1} MiB", "heap (frozen)", heap as f64 / (1 << 20) as f64);
+        timed("get (frozen)", || {
+            for word in &words {
+                black_box(frozen.get(word));
+            }
+        });
+        timed("iter_prefix (frozen)", || {
+            for word in words.iter().take(400) {
+                black_box(frozen.iter_prefix(first_chars(word, 3)).count());
+            }
+        });
+        drop(frozen);
+    }
+
+    let mut trie = trie;
+    timed("remove", || {
+        for word in &words {
+            black_box(trie.remove(word));
+        }
+    });
+
+    let text = "abc-defgh\t".repeat(1000);
+    let prefixes = text.char_indices().skip(1).step_by(10).map(|(i, _)| &text[..i]).collect::<Vec<_>>();
+    let mut deep: Trie<usize> = Trie::new();
+    timed("insert (deep)", || {
+        for (i, prefix) in prefixes.iter().enumerate() {
+            deep.insert(prefix, Some(i));
+        }
+    });
+    timed("get (deep)", || {
+        for prefix in &prefixes {
+            black_box(deep.get(prefix));
+        }
+    });
+    timed("suffix_tree (deep)", || {
+        for prefix in prefixes.iter().step_by(10) {
+            black_box(deep.suffix_tree(prefix));
+        }
+    });
+    timed("get_suffixes_values (deep)", || {
+        for prefix in prefixes.iter().step_by(100) {
+            black_box(deep.get_suffixes_values(prefix));
+        }
+    });
+}
@@ -0,0 +1,21 @@
+#!/bin/sh
+# runs benches/dictionary.rs against the tree just before the children moved from a `HashMap` per node into
+# sorted vectors, the "before" numbers of that change come from here and the "after" ones from
+# `cargo bench --bench dictionary` on the same machine. `DICTIONARY` is passed through.
+#
+#   sh benches/layout_baseline.sh [<revision>]
+#
+# the revision defaults to the parent of the commit which introduced the sorted vectors. it is checked out
+# in a worktree under target/, given the current bench (the frozen trie, which came later, is left out
+# with `--cfg layout_baseline`) and benchmarked in release mode.
+set -e
+cd "$(git rev-parse --show-toplevel)"
+revision=${1:-$(git rev-parse ':/^\[user-017\] Keep children in sorted vectors')^}
+worktree=target/layout-baseline
+git worktree remove --force "$worktree" 2>/dev/null || true
+git worktree add --detach "$worktree" "$revision"
+mkdir -p "$worktree/benches"
+cp -R benches/dictionary.rs benches/corpus "$worktree/benches/"
+grep -q 'name = "dictionary"' "$worktree/Cargo.toml" || printf '\n[[bench]]\nname = "dictionary"\nharness = false\n' >> "$worktree/Cargo.toml"
+cd "$worktree"
+RUSTFLAGS="--cfg layout_baseline" cargo bench --bench dictionary
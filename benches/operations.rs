@@ -0,0 +1,152 @@
+//! criterion benchmarks of the main operations over generated corpora, against `BTreeMap` and `HashMap`
+//!
+//! `cargo bench --bench operations` runs every corpus (words, urls, paths, emoji) at 10k, 100k and 1M keys,
+//! which takes a while. `BENCH_SIZES=10000,100000` picks the sizes and criterion filters by name,
+//! e.g. `cargo bench --bench operations -- 'get/urls'`.
+//! the reports under `target/criterion` keep the previous run, so a slower release shows up as a regression.
+use std::collections::{BTreeMap, HashMap};
+use std::hint::black_box;
+use std::ops::Bound;
+use std::time::Duration;
+use ab_radix_trie::{MatchingOptions, Trie};
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput};
+
+mod corpus;
+
+const SIZES: [usize; 3] = [10_000, 100_000, 1_000_000];
+/// how many prefixes the prefix and fuzzy queries run with, and how many chars they have
+const PREFIXES: usize = 100;
+const PREFIX_CHARS: usize = 3;
+
+fn sizes() -> Vec<usize> {
+    match std::env::var("BENCH_SIZES") {
+        Ok(sizes) => sizes.split(',').map(|size| size.trim().parse().expect("a comma separated list of sizes")).collect(),
+        Err(_) => SIZES.to_vec(),
+    }
+}
+
+fn trie_of(keys: &[String]) -> Trie<u32> {
+    let mut trie = Trie::new();
+    for (i, key) in keys.iter().enumerate() {
+        trie.insert(key.as_str(), Some(i as u32));
+    }
+    trie
+}
+
+fn btree_of(keys: &[String]) -> BTreeMap<String, u32> {
+    keys.iter().enumerate().map(|(i, key)| (key.clone(), i as u32)).collect()
+}
+
+fn hash_of(keys: &[String]) -> HashMap<String, u32> {
+    keys.iter().enumerate().map(|(i, key)| (key.clone(), i as u32)).collect()
+}
+
+fn benchmarks(c: &mut Criterion) {
+    let sizes = sizes();
+    for kind in corpus::KINDS {
+        for &size in &sizes {
+            let keys = corpus::generate(kind, size);
+            let prefixes = corpus::prefixes(&keys, PREFIXES, PREFIX_CHARS);
+            let trie = trie_of(&keys);
+            let btree = btree_of(&keys);
+            let hash = hash_of(&keys);
+
+            let mut group = c.benchmark_group(format!("insert/{kind}"));
+            group.throughput(Throughput::Elements(size as u64));
+            group.sample_size(10);
+            group.bench_with_input(BenchmarkId::new("trie", size), &keys, |b, keys| b.iter(|| trie_of(keys)));
+            group.bench_with_input(BenchmarkId::new("btreemap", size), &keys, |b, keys| b.iter(|| btree_of(keys)));
+            group.bench_with_input(BenchmarkId::new("hashmap", size), &keys, |b, keys| b.iter(|| hash_of(keys)));
+            group.finish();
+
+            let mut group = c.benchmark_group(format!("get/{kind}"));
+            group.throughput(Throughput::Elements(size as u64));
+            group.sample_size(10);
+            group.bench_with_input(BenchmarkId::new("trie", size), &keys, |b, keys| b.iter(|| {
+                keys.iter().filter_map(|key| trie.get(key.as_str())).count()
+            }));
+            group.bench_with_input(BenchmarkId::new("btreemap", size), &keys, |b, keys| b.iter(|| {
+                keys.iter().filter_map(|key| btree.get(key)).count()
+            }));
+            group.bench_with_input(BenchmarkId::new("hashmap", size), &keys, |b, keys| b.iter(|| {
+                keys.iter().filter_map(|key| hash.get(key)).count()
+            }));
+            group.finish();
+
+            // a hash map has no order to find the keys starting with a prefix by, so it is not compared
+            let mut group = c.benchmark_group(format!("prefix/{kind}"));
+            group.throughput(Throughput::Elements(prefixes.len() as u64));
+            group.bench_with_input(BenchmarkId::new("trie get_suffixes_values", size), &prefixes, |b, prefixes| b.iter(|| {
+                prefixes.iter().filter_map(|prefix| trie.get_suffixes_values(prefix)).map(|suffixes| suffixes.len()).sum::<usize>()
+            }));
+            group.bench_with_input(BenchmarkId::new("trie iter_prefix", size), &prefixes, |b, prefixes| b.iter(|| {
+                prefixes.iter().map(|prefix| trie.iter_prefix(prefix).count()).sum::<usize>()
+            }));
+            group.bench_with_input(BenchmarkId::new("btreemap", size), &prefixes, |b, prefixes| b.iter(|| {
+                prefixes.iter().map(|prefix| {
+                    btree.range::<str, _>((Bound::Included(prefix.as_str()), Bound::Unbounded)).take_while(|(key, _)| key.starts_with(prefix.as_str())).count()
+                }).sum::<usize>()
+            }));
+            group.finish();
+
+            let options = MatchingOptions::ignoring_white_space();
+            let mut group = c.benchmark_group(format!("fuzzy/{kind}"));
+            group.throughput(Throughput::Elements(prefixes.len() as u64));
+            group.bench_with_input(BenchmarkId::new("trie get_suffixes_with_matching_options", size), &prefixes, |b, prefixes| b.iter(|| {
+                prefixes.iter().filter_map(|prefix| trie.get_suffixes_with_matching_options(prefix, &options)).map(|suffixes| suffixes.len()).sum::<usize>()
+            }));
+            group.finish();
+
+            // the copies to remove from are made outside of the measurement
+            let mut group = c.benchmark_group(format!("remove/{kind}"));
+            group.throughput(Throughput::Elements(size as u64));
+            group.sample_size(10);
+            group.bench_with_input(BenchmarkId::new("trie", size), &keys, |b, keys| b.iter_batched_ref(|| trie.clone(), |trie| {
+                keys.iter().filter_map(|key| trie.remove(key.as_str())).count()
+            }, BatchSize::PerIteration));
+            group.bench_with_input(BenchmarkId::new("btreemap", size), &keys, |b, keys| b.iter_batched_ref(|| btree.clone(), |btree| {
+                keys.iter().filter_map(|key| btree.remove(key)).count()
+            }, BatchSize::PerIteration));
+            group.bench_with_input(BenchmarkId::new("hashmap", size), &keys, |b, keys| b.iter_batched_ref(|| hash.clone(), |hash| {
+                keys.iter().filter_map(|key| hash.remove(key)).count()
+            }, BatchSize::PerIteration));
+            group.finish();
+
+            #[cfg(feature = "serde")]
+            {
+                let mut group = c.benchmark_group(format!("serde/{kind}"));
+                group.throughput(Throughput::Elements(size as u64));
+                group.sample_size(10);
+                group.measurement_time(Duration::from_secs(10));
+                group.bench_with_input(BenchmarkId::new("trie json", size), &trie, |b, trie| b.iter(|| {
+                    let json = serde_json::to_string(trie).unwrap();
+                    serde_json::from_str::<Trie<u32>>(&json).unwrap()
+                }));
+                group.bench_with_input(BenchmarkId::new("trie flat json", size), &trie, |b, trie| b.iter(|| {
+                    let mut json = Vec::new();
+                    ab_radix_trie::flat::serialize(trie, &mut serde_json::Serializer::new(&mut json)).unwrap();
+                    ab_radix_trie::flat::deserialize::<u32, str, _>(&mut serde_json::Deserializer::from_slice(&json)).unwrap()
+                }));
+                group.bench_with_input(BenchmarkId::new("btreemap json", size), &btree, |b, btree| b.iter(|| {
+                    let json = serde_json::to_string(btree).unwrap();
+                    serde_json::from_str::<BTreeMap<String, u32>>(&json).unwrap()
+                }));
+                #[cfg(feature = "std")]
+                group.bench_with_input(BenchmarkId::new("trie binary", size), &trie, |b, trie| b.iter(|| {
+                    let mut bytes = Vec::new();
+                    trie.write_to(&mut bytes).unwrap();
+                    Trie::<u32>::read_from(bytes.as_slice()).unwrap()
+                }));
+                group.finish();
+            }
+            black_box((trie, btree, hash));
+        }
+    }
+}
+
+criterion_group! {
+    name = operations;
+    config = Criterion::default().warm_up_time(Duration::from_secs(1));
+    targets = benchmarks
+}
+criterion_main!(operations);
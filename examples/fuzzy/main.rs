@@ -13,5 +13,12 @@ fn main() {
     let results = trie.get_suffixes_with_matching_options("roma", &MatchingOptions::ignoring_white_space());
     // should return "romanus" and "rom anus"
     println!("{:?}", results);
+
+    // every branch compatible with the options, with the full keys
+    let keys = trie.get_keys_with_matching_options("roma", &MatchingOptions::ignoring_white_space())
+        .into_iter()
+        .map(|entry| entry.key)
+        .collect::<Vec<_>>();
+    println!("{:?}", keys);
 }
 
@@ -1,5 +1,4 @@
 use ab_radix_trie::Trie;
-use serde_json;
 use serde_json::json;
 fn main() {
     let mut trie: Trie<i32> = Trie::new();
@@ -0,0 +1,23 @@
+//! arbitrary bytes read as the binary encoding (`Trie::read_from`) and as a frozen image (`MappedTrie`)
+//! whatever they hold, reading must fail cleanly or give a trie which is consistent
+#![no_main]
+use ab_radix_trie::image::MappedTrie;
+use ab_radix_trie::Trie;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(trie) = Trie::<u8>::read_from(data) {
+        assert_eq!(trie.validate(), Ok(()));
+        let mut bytes = Vec::new();
+        trie.write_to(&mut bytes).unwrap();
+        let copy = Trie::<u8>::read_from(bytes.as_slice()).unwrap();
+        assert!(copy.iter().eq(trie.iter()));
+    }
+    if let Ok(mapped) = MappedTrie::new(data) {
+        assert_eq!(mapped.iter().count(), mapped.len());
+        for (key, value) in mapped.iter() {
+            assert_eq!(mapped.get(&key), value);
+            assert_eq!(mapped.iter_prefix(&key).next().map(|(first, _)| first), Some(key));
+        }
+    }
+});
@@ -0,0 +1,43 @@
+//! arbitrary JSON deserialized into a trie, which must not panic
+//! the derived representation is taken as it is, so the trie may not validate (a node whose text does not start
+//! with its char in the parent, an empty text, a non terminal leaf, ...). such a trie is still queried, taken apart
+//! and refilled, which must not panic either, but its results are only checked when it validates
+#![no_main]
+use ab_radix_trie::Trie;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(mut trie) = serde_json::from_slice::<Trie<u8>>(data) else {
+        return
+    };
+    let valid = trie.validate().is_ok();
+    let keys = trie.keys().collect::<Vec<_>>();
+    if valid {
+        assert!(keys.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(keys.len(), trie.len());
+    }
+    for key in &keys {
+        let contained = trie.contains_key(key);
+        let longest = trie.longest_prefix_match(key).map(|(prefix, _)| prefix);
+        let suffixes = trie.get_suffixes_values(key);
+        if valid {
+            assert!(contained);
+            assert_eq!(longest, Some(key.as_str()));
+            assert!(suffixes.is_some_and(|suffixes| suffixes.iter().any(|entry| entry.key.is_empty())));
+        }
+    }
+    for key in &keys {
+        trie.remove(key);
+        if valid {
+            assert_eq!(trie.validate(), Ok(()));
+        }
+    }
+    if valid {
+        assert!(trie.is_empty());
+    }
+    // whatever is left of a trie which did not validate still takes new keys
+    for key in &keys {
+        trie.insert(key, Some(0));
+        trie.get(key);
+    }
+});
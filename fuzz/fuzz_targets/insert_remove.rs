@@ -0,0 +1,33 @@
+//! insert, remove and lookup sequences decoded from the input, checked against a `BTreeMap`
+//! every input byte pair is an operation and a key length, followed by that many symbols
+#![no_main]
+use std::collections::BTreeMap;
+use ab_radix_trie::Trie;
+use libfuzzer_sys::fuzz_target;
+
+/// few symbols (several of them multibyte) so the keys share prefixes
+const SYMBOLS: [&str; 8] = ["a", "b", "ab", "é", "ß", "🤡", "e\u{301}", "\u{10ffff}"];
+
+fuzz_target!(|data: &[u8]| {
+    let mut trie: Trie<u8> = Trie::new();
+    let mut model: BTreeMap<String, Option<u8>> = BTreeMap::new();
+    let mut bytes = data.iter().copied();
+    while let (Some(op), Some(len)) = (bytes.next(), bytes.next()) {
+        let key = bytes.by_ref().take(len as usize % 8).map(|b| SYMBOLS[b as usize % SYMBOLS.len()]).collect::<String>();
+        match op % 4 {
+            0 | 1 => {
+                let value = (op >= 128).then_some(op);
+                assert_eq!(trie.insert(&key, value), model.insert(key, value).flatten());
+            }
+            2 => assert_eq!(trie.remove(&key), model.remove(&key).flatten()),
+            _ => {
+                assert_eq!(trie.get(&key), model.get(&key).and_then(Option::as_ref));
+                let expected = model.range(key.clone()..).take_while(|(other, _)| other.starts_with(&key)).map(|(other, _)| other.clone());
+                assert!(trie.iter_prefix(&key).map(|(other, _)| other).eq(expected));
+            }
+        }
+        assert_eq!(trie.validate(), Ok(()));
+        assert_eq!(trie.len(), model.len());
+    }
+    assert!(trie.keys().eq(model.keys().cloned()));
+});
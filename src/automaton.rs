@@ -0,0 +1,103 @@
+//! the guided walk shared by the glob patterns and the regular expressions
+//!
+//! an automaton keeps the set of its states (small integers) which are still alive for the text
+//! read so far. the walk drops a subtree as soon as the set is empty and, while only literal chars
+//! can come next, just visits the children starting with those chars.
+use alloc::string::String;
+use alloc::vec::Vec;
+use crate::iter::stack_order;
+use crate::map::CharMap;
+use crate::{Node, Trie};
+
+pub(crate) trait Automaton {
+    /// the states alive before reading any char
+    fn start(&self) -> Vec<usize>;
+
+    /// the states alive after reading one more char, empty once nothing can match anymore
+    fn step(&self, states: &[usize], c: char) -> Vec<usize>;
+
+    fn accepts(&self, states: &[usize]) -> bool;
+
+    /// the only chars which can be read next, None if any other char might match as well
+    fn next_chars(&self, states: &[usize]) -> Option<Vec<char>>;
+
+    /// true if the automaton matches the whole text
+    fn is_match(&self, text: &str) -> bool {
+        let states = text.chars().try_fold(self.start(), |states, c| {
+            Some(self.step(&states, c)).filter(|states| !states.is_empty())
+        });
+        states.is_some_and(|states| self.accepts(&states))
+    }
+}
+
+/// a set of chars given by ranges, e.g. `[a-z_]`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct CharClass {
+    pub(crate) negated: bool,
+    pub(crate) ranges: Vec<(char, char)>,
+}
+
+impl CharClass {
+    pub(crate) fn matches(&self, c: char) -> bool {
+        self.ranges.iter().any(|(from, to)| (*from..=*to).contains(&c)) != self.negated
+    }
+}
+
+/// iterator over the `(key, value)` matching a [`Pattern`](crate::pattern::Pattern)
+/// or a [`Regex`](crate::regex::Regex), in lexicographic order
+pub struct Matches<'a, 'p, V> {
+    automaton: &'p dyn Automaton,
+    /// nodes still to visit with the length of the key leading up to them and the alive states before their text
+    stack: Vec<(&'a Node<V>, usize, Vec<usize>)>,
+    key: String,
+}
+
+impl<'a, 'p, V> Matches<'a, 'p, V> {
+    pub(crate) fn new(trie: &'a Trie<V>, automaton: &'p dyn Automaton) -> Self {
+        let mut matches = Matches { automaton, stack: Vec::new(), key: String::new() };
+        let start = automaton.start();
+        matches.push_children(&trie.children, 0, start.clone());
+        // the empty key comes before everything else
+        matches.stack.extend(trie.empty.as_ref().map(|empty| (empty, 0, start)));
+        matches
+    }
+
+    /// pushes the children which can match (largest first, so the smallest pops first)
+    fn push_children(&mut self, children: &'a CharMap<char, Node<V>>, len: usize, states: Vec<usize>) {
+        let candidates = match self.automaton.next_chars(&states) {
+            Some(mut chars) => {
+                chars.sort_unstable_by(|x, y| y.cmp(x));
+                chars.dedup();
+                chars.iter().filter_map(|c| children.get(c)).collect()
+            }
+            None => stack_order(children),
+        };
+        self.stack.extend(candidates.into_iter().map(|child| (child, len, states.clone())));
+    }
+}
+
+impl<'a, V> Iterator for Matches<'a, '_, V> {
+    type Item = (String, Option<&'a V>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((node, len, mut states)) = self.stack.pop() {
+            for c in node.text.chars() {
+                states = self.automaton.step(&states, c);
+                if states.is_empty() {
+                    break
+                }
+            }
+            if states.is_empty() {
+                continue
+            }
+            self.key.truncate(len);
+            self.key.push_str(node.text.as_str());
+            let accepts = node.terminal && self.automaton.accepts(&states);
+            self.push_children(&node.children, self.key.len(), states);
+            if accepts {
+                return Some((self.key.clone(), node.value.as_ref()))
+            }
+        }
+        None
+    }
+}
@@ -0,0 +1,326 @@
+//! a compact, versioned binary encoding of a trie, see [`Trie::write_to`] and [`Trie::read_from`]
+//!
+//! only the keys, the structure and the values are written, the counters and the bounds are
+//! rebuilt on load. the stream is
+//!
+//! * the magic `AB-TRIS\0` and the format version (a little endian u32)
+//! * the key count
+//! * the nodes in pre-order (children by increasing first char), each as its text (omitted for the
+//!   root level, which holds the empty key), a flags byte (terminal, has a value), the value and
+//!   the number of children
+//! * the CRC-32 of everything before it (a little endian u32)
+//!
+//! counts and lengths are LEB128 varints and a value is its length followed by the bytes of [`BinaryValue::encode`].
+//! a stream is only accepted in the canonical form `write_to` produces: sorted children, non empty texts and no
+//! node which could be merged with its only child.
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use std::io::{self, Read, Write};
+use crate::image::Crc32;
+use crate::{Node, Trie, TrieError};
+
+const MAGIC: &[u8; 8] = b"AB-TRIS\0";
+/// the version written by `write_to`, streams of any other version are rejected
+pub const VERSION: u32 = 1;
+const TERMINAL: u8 = 1;
+const HAS_VALUE: u8 = 2;
+
+/// a value which can be written with [`Trie::write_to`] and read back with [`Trie::read_from`]
+///
+/// implemented for the primitive numbers, `bool`, `char`, `String`, `Vec<u8>` and `()`
+pub trait BinaryValue: Sized {
+    /// appends the bytes of the value
+    fn encode(&self, out: &mut Vec<u8>);
+
+    /// None if the bytes are not the encoding of a value
+    fn decode(bytes: &[u8]) -> Option<Self>;
+}
+
+macro_rules! binary_number {
+    ($($t:ty),*) => {$(
+        impl BinaryValue for $t {
+            fn encode(&self, out: &mut Vec<u8>) {
+                out.extend_from_slice(&self.to_le_bytes());
+            }
+
+            fn decode(bytes: &[u8]) -> Option<Self> {
+                Some(<$t>::from_le_bytes(bytes.try_into().ok()?))
+            }
+        }
+    )*};
+}
+
+binary_number!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128, f32, f64);
+
+/// written as 64 bits whatever the platform
+impl BinaryValue for usize {
+    fn encode(&self, out: &mut Vec<u8>) {
+        (*self as u64).encode(out)
+    }
+
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        u64::decode(bytes)?.try_into().ok()
+    }
+}
+
+/// written as 64 bits whatever the platform
+impl BinaryValue for isize {
+    fn encode(&self, out: &mut Vec<u8>) {
+        (*self as i64).encode(out)
+    }
+
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        i64::decode(bytes)?.try_into().ok()
+    }
+}
+
+impl BinaryValue for bool {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.push(*self as u8)
+    }
+
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        match bytes {
+            [0] => Some(false),
+            [1] => Some(true),
+            _ => None,
+        }
+    }
+}
+
+impl BinaryValue for char {
+    fn encode(&self, out: &mut Vec<u8>) {
+        (*self as u32).encode(out)
+    }
+
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        char::from_u32(u32::decode(bytes)?)
+    }
+}
+
+impl BinaryValue for String {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(self.as_bytes())
+    }
+
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        String::from_utf8(bytes.to_vec()).ok()
+    }
+}
+
+impl BinaryValue for Vec<u8> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(self)
+    }
+
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        Some(bytes.to_vec())
+    }
+}
+
+impl BinaryValue for () {
+    fn encode(&self, _: &mut Vec<u8>) {}
+
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        bytes.is_empty().then_some(())
+    }
+}
+
+/// the error of a stream which does not hold a valid trie, the `TrieError` is its inner error
+fn invalid(error: TrieError) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
+
+fn corrupt(reason: &'static str) -> io::Error {
+    invalid(TrieError::Corrupt { reason })
+}
+
+fn push_varint(out: &mut Vec<u8>, mut n: u64) {
+    while n >= 0x80 {
+        out.push(n as u8 | 0x80);
+        n >>= 7;
+    }
+    out.push(n as u8);
+}
+
+/// checksums everything passing through
+struct Checked<T> {
+    inner: T,
+    crc: Crc32,
+}
+
+impl<W: Write> Checked<W> {
+    fn put(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.crc.update(bytes);
+        self.inner.write_all(bytes)
+    }
+}
+
+impl<R: Read> Checked<R> {
+    fn bytes<const N: usize>(&mut self) -> io::Result<[u8; N]> {
+        let mut bytes = [0; N];
+        self.inner.read_exact(&mut bytes)?;
+        self.crc.update(&bytes);
+        Ok(bytes)
+    }
+
+    fn varint(&mut self) -> io::Result<u64> {
+        let mut n = 0_u64;
+        for shift in (0..64).step_by(7) {
+            let [b] = self.bytes()?;
+            let bits = (b & 0x7f) as u64;
+            if bits << shift >> shift != bits {
+                break
+            }
+            n |= bits << shift;
+            if b & 0x80 == 0 {
+                return Ok(n)
+            }
+        }
+        Err(corrupt("varint overflow"))
+    }
+
+    /// reads a length prefixed run of bytes, allocating as the bytes arrive so a bad length cannot exhaust the memory
+    fn run(&mut self) -> io::Result<Vec<u8>> {
+        let len = self.varint()?;
+        let mut bytes = Vec::new();
+        (&mut self.inner).take(len).read_to_end(&mut bytes)?;
+        if bytes.len() as u64 != len {
+            return Err(io::ErrorKind::UnexpectedEof.into())
+        }
+        self.crc.update(&bytes);
+        Ok(bytes)
+    }
+
+    /// reads the flags and the value of a node, the value is None for a node which is not a key
+    fn key<V: BinaryValue>(&mut self) -> io::Result<Option<Option<V>>> {
+        let [flags] = self.bytes()?;
+        match flags {
+            0 => Ok(None),
+            TERMINAL => Ok(Some(None)),
+            flags if flags == TERMINAL | HAS_VALUE => {
+                let value = V::decode(&self.run()?).ok_or_else(|| corrupt("invalid value"))?;
+                Ok(Some(Some(value)))
+            }
+            _ => Err(corrupt("invalid flags")),
+        }
+    }
+}
+
+/// appends the flags and the value of a node
+fn push_key<V: BinaryValue>(out: &mut Vec<u8>, scratch: &mut Vec<u8>, terminal: bool, value: Option<&V>) {
+    match value {
+        Some(value) if terminal => {
+            out.push(TERMINAL | HAS_VALUE);
+            scratch.clear();
+            value.encode(scratch);
+            push_varint(out, scratch.len() as u64);
+            out.extend_from_slice(scratch);
+        }
+        _ => out.push(if terminal { TERMINAL } else { 0 }),
+    }
+}
+
+impl<V: BinaryValue> Trie<V> {
+    /// writes the keys and values in the format of [`codec`](crate::codec), visit counts are not written
+    ///
+    /// ```
+    /// use ab_radix_trie::Trie;
+    /// let mut trie: Trie<u32> = Trie::new();
+    /// trie.insert("romanus", Some(1));
+    /// trie.insert("romulus", None);
+    /// let mut bytes = Vec::new();
+    /// trie.write_to(&mut bytes).unwrap();
+    /// let copy: Trie<u32> = Trie::read_from(bytes.as_slice()).unwrap();
+    /// assert_eq!(copy.iter().collect::<Vec<_>>(), trie.iter().collect::<Vec<_>>());
+    /// ```
+    pub fn write_to(&self, writer: impl Write) -> io::Result<()> {
+        let mut out = Checked { inner: writer, crc: Crc32::new() };
+        let mut record = Vec::new();
+        let mut scratch = Vec::new();
+        record.extend_from_slice(MAGIC);
+        record.extend_from_slice(&VERSION.to_le_bytes());
+        push_varint(&mut record, self.key_count as u64);
+        let empty = self.empty.as_ref();
+        push_key(&mut record, &mut scratch, empty.is_some(), empty.and_then(|empty| empty.value.as_ref()));
+        push_varint(&mut record, self.children.len() as u64);
+        out.put(&record)?;
+        for child in self.children.values() {
+            write_node(child, &mut out, &mut record, &mut scratch)?;
+        }
+        let checksum = out.crc.finish();
+        out.inner.write_all(&checksum.to_le_bytes())
+    }
+
+    /// reads a trie written by [`Trie::write_to`], rebuilding the counters
+    /// a stream which is truncated, damaged or not in the canonical form fails with `io::ErrorKind::InvalidData`
+    /// (or `UnexpectedEof`) and a [`TrieError`] as the inner error, nothing is read past the checksum
+    pub fn read_from(reader: impl Read) -> io::Result<Self> {
+        let mut input = Checked { inner: reader, crc: Crc32::new() };
+        if &input.bytes::<8>()? != MAGIC {
+            return Err(corrupt("not a trie stream"))
+        }
+        let version = u32::from_le_bytes(input.bytes()?);
+        if version != VERSION {
+            return Err(invalid(TrieError::UnsupportedVersion { version }))
+        }
+        let key_count = input.varint()?;
+        let mut trie = Trie::new();
+        if let Some(value) = input.key()? {
+            trie.insert("", value);
+        }
+        let mut nodes = 0;
+        let mut key = String::new();
+        // per level of the nodes being read: the length of the key above, the children left and the last first char
+        let mut levels = vec![(0, input.varint()?, None)];
+        while let Some((len, left, last)) = levels.last_mut() {
+            if *left == 0 {
+                levels.pop();
+                continue
+            }
+            *left -= 1;
+            key.truncate(*len);
+            let text = String::from_utf8(input.run()?).map_err(|_| corrupt("text is not utf-8"))?;
+            let first = text.chars().next().ok_or_else(|| corrupt("empty text"))?;
+            if last.is_some_and(|last| last >= first) {
+                return Err(corrupt("children are not sorted"))
+            }
+            *last = Some(first);
+            key.push_str(&text);
+            let value = input.key()?;
+            let children = input.varint()?;
+            if value.is_none() && children < 2 {
+                return Err(corrupt("uncompressed node"))
+            }
+            if let Some(value) = value {
+                trie.insert(&key, value);
+            }
+            nodes += 1;
+            levels.push((key.len(), children, None));
+        }
+        let checksum = input.crc.finish();
+        let mut stored = [0; 4];
+        input.inner.read_exact(&mut stored)?;
+        if u32::from_le_bytes(stored) != checksum {
+            return Err(corrupt("checksum mismatch"))
+        }
+        if trie.len() as u64 != key_count || trie.node_count != nodes {
+            return Err(corrupt("counts do not match the keys"))
+        }
+        Ok(trie)
+    }
+}
+
+fn write_node<V: BinaryValue>(node: &Node<V>, out: &mut Checked<impl Write>, record: &mut Vec<u8>, scratch: &mut Vec<u8>) -> io::Result<()> {
+    record.clear();
+    push_varint(record, node.text.len() as u64);
+    record.extend_from_slice(node.text.as_bytes());
+    push_key(record, scratch, node.terminal, node.value.as_ref());
+    push_varint(record, node.children.len() as u64);
+    out.put(record)?;
+    for child in node.children.values() {
+        write_node(child, out, record, scratch)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,227 @@
+//! in-place access to a single key of the trie, modeled on `std::collections::hash_map::Entry`
+use alloc::vec::Vec;
+use core::borrow::Borrow;
+use crate::map::{CharMap, Entry as CharMapEntry};
+use crate::rank::MaxValue;
+use crate::{Node, Trie, TrieKey};
+
+/// a view into a single key of a [`Trie`], either present (occupied) or missing (vacant)
+///
+/// since keys may be inserted without a value, an occupied entry does not necessarily hold a value.
+/// `or_insert` and friends fill the value in that case.
+pub enum Entry<'a, V, K: ?Sized + TrieKey = str> {
+    Occupied(OccupiedEntry<'a, V, K>),
+    Vacant(VacantEntry<'a, V, K>),
+}
+
+/// an entry for a key which is present in the trie
+pub struct OccupiedEntry<'a, V, K: ?Sized + TrieKey = str> {
+    key: K::Owned,
+    node: &'a mut Node<V, K>,
+}
+
+/// an entry for a key which is not in the trie yet
+///
+/// it holds on to the place the key goes and the nodes above it, so inserting does not walk the key again
+pub struct VacantEntry<'a, V, K: ?Sized + TrieKey = str> {
+    key: K::Owned,
+    slot: Slot<'a, V, K>,
+    /// the nodes the key passes through on the way to the slot, whose weight and bound grow with it
+    ancestors: Vec<Ancestor<'a>>,
+    counts: Counts<'a>,
+}
+
+/// the counters of the trie
+struct Counts<'a> {
+    nodes: &'a mut usize,
+    chars: &'a mut usize,
+    keys: &'a mut usize,
+}
+
+/// where a vacant key goes
+enum Slot<'a, V, K: ?Sized + TrieKey> {
+    /// the empty key
+    Empty(&'a mut Option<Node<V, K>>),
+    /// the root level, which has no child for the first symbol of the key
+    Root(&'a mut CharMap<K::Symbol, Node<V, K>>),
+    /// the node the key ends in (without being a key) or leaves, `at` is the offset of the key it starts at
+    Node { node: &'a mut Node<V, K>, at: usize },
+}
+
+struct Ancestor<'a> {
+    weight: &'a mut usize,
+    max_len: &'a mut usize,
+    /// the offset of the key the node starts at
+    at: usize,
+}
+
+impl<'a, V, K: ?Sized + TrieKey> Entry<'a, V, K> {
+    /// returns the key of this entry
+    pub fn key(&self) -> &K {
+        match self {
+            Entry::Occupied(entry) => entry.key(),
+            Entry::Vacant(entry) => entry.key(),
+        }
+    }
+
+    /// makes sure the key holds a value by inserting the default if needed and returns a mutable reference to it
+    pub fn or_insert(self, default: V) -> &'a mut V {
+        self.or_insert_with(|| default)
+    }
+
+    /// like `or_insert` but only computes the default when it is needed
+    pub fn or_insert_with<F: FnOnce() -> V>(self, default: F) -> &'a mut V {
+        match self {
+            Entry::Occupied(entry) => entry.node.value.get_or_insert_with(default),
+            Entry::Vacant(entry) => entry.insert(default()),
+        }
+    }
+
+    /// calls `f` with the value of an occupied entry (if it holds one) before any `or_insert`
+    pub fn and_modify<F: FnOnce(&mut V)>(mut self, f: F) -> Self {
+        if let Entry::Occupied(entry) = &mut self {
+            if let Some(value) = entry.get_mut() {
+                f(value);
+            }
+        }
+        self
+    }
+}
+
+impl<'a, V: Default, K: ?Sized + TrieKey> Entry<'a, V, K> {
+    /// makes sure the key holds a value by inserting `V::default()` if needed
+    pub fn or_default(self) -> &'a mut V {
+        self.or_insert_with(V::default)
+    }
+}
+
+impl<'a, V, K: ?Sized + TrieKey> OccupiedEntry<'a, V, K> {
+    pub fn key(&self) -> &K {
+        self.key.borrow()
+    }
+
+    /// the value of this key, None if the key was inserted without a value
+    pub fn get(&self) -> Option<&V> {
+        self.node.value.as_ref()
+    }
+
+    pub fn get_mut(&mut self) -> Option<&mut V> {
+        self.node.value.as_mut()
+    }
+
+    /// converts the entry into a mutable reference to its value bound to the lifetime of the trie
+    pub fn into_mut(self) -> Option<&'a mut V> {
+        self.node.value.as_mut()
+    }
+
+    /// replaces the value of this key and returns the previous one
+    pub fn insert(&mut self, value: V) -> Option<V> {
+        self.node.value.replace(value)
+    }
+}
+
+impl<'a, V, K: ?Sized + TrieKey> VacantEntry<'a, V, K> {
+    pub fn key(&self) -> &K {
+        self.key.borrow()
+    }
+
+    pub fn into_key(self) -> K::Owned {
+        self.key
+    }
+
+    /// inserts the key with the value (splitting nodes as `Trie::insert` does) and returns a mutable reference to the value
+    pub fn insert(self, value: V) -> &'a mut V {
+        let VacantEntry { key, slot, mut ancestors, counts } = self;
+        let key: &K = key.borrow();
+        // the length in symbols of the rest of the key from an offset, counted forward along the ancestors
+        let (mut symbols, mut counted) = (key.symbol_count(), 0);
+        let mut symbols_from = |at: usize| {
+            symbols -= key.split_key_at(at).0.split_key_at(counted).1.symbol_count();
+            counted = at;
+            symbols
+        };
+        for ancestor in ancestors.iter_mut() {
+            *ancestor.max_len = (*ancestor.max_len).max(symbols_from(ancestor.at));
+        }
+        let chars_before = *counts.chars;
+        let node = match slot {
+            Slot::Empty(empty) => empty.insert(Node::new(key, false, None)),
+            Slot::Root(children) => {
+                *counts.nodes += 1;
+                *counts.chars += key.key_len();
+                match children.entry(key.first_symbol().unwrap()) {
+                    CharMapEntry::Vacant(slot) => slot.insert(Node::new(key, false, None)),
+                    CharMapEntry::Occupied(child) => child.into_mut(),
+                }
+            }
+            Slot::Node { node, at } => node.insert_node(key.split_key_at(at).1, symbols_from(at), counts.nodes, counts.chars),
+        };
+        for ancestor in ancestors {
+            *ancestor.weight += *counts.chars - chars_before;
+        }
+        node.terminal = true;
+        *counts.keys += 1;
+        node.value.insert(value)
+    }
+}
+
+impl<V, K: ?Sized + TrieKey> Trie<V, K> {
+    /// returns the entry for the key for in-place manipulation
+    /// ```
+    /// use ab_radix_trie::Trie;
+    /// let mut counts: Trie<u32> = Trie::new();
+    /// for word in ["ruber", "rubens", "ruber"] {
+    ///     *counts.entry(word).or_insert(0) += 1;
+    /// }
+    /// assert_eq!(counts.get("ruber"), Some(&2));
+    /// assert_eq!(counts.get("rubens"), Some(&1));
+    /// ```
+    pub fn entry(&mut self, key: &K) -> Entry<'_, V, K> {
+        // inserting a vacant key may move nodes, which the pending visits are recorded by
+        self.merge_visits();
+        let mut visits = self.visits();
+        let Trie { children, node_count, char_count, key_count, empty, visit_tracker, .. } = self;
+        let counts = Counts { nodes: node_count, chars: char_count, keys: key_count };
+        let vacant = |slot, ancestors| Entry::Vacant(VacantEntry { key: key.to_owned_key(), slot, ancestors, counts });
+        let Some(first) = key.first_symbol() else {
+            return match empty {
+                Some(node) => {
+                    node.max_value_at = MaxValue::Stale;
+                    Entry::Occupied(OccupiedEntry { key: key.to_owned_key(), node })
+                }
+                None => vacant(Slot::Empty(empty), Vec::new()),
+            }
+        };
+        if !children.contains_key(&first) {
+            return vacant(Slot::Root(children), Vec::new())
+        }
+        // a single walk down the key, the children are looked up before descending since a mutable borrow
+        // cannot be handed out on one branch and used on the other
+        let mut node = children.get_mut(&first).unwrap();
+        let mut ancestors = Vec::new();
+        let mut at = 0;
+        loop {
+            visits.record(node);
+            // the value of the entry may change or a key be added below, the largest values are found again
+            // by the next `insert` or `remove`
+            node.max_value_at = MaxValue::Stale;
+            let next = key.split_key_at(at).1.strip_key_prefix(node.text.borrow()).map(|rest| rest.first_symbol());
+            match next {
+                Some(None) if node.terminal => {
+                    visits.record_into(visit_tracker);
+                    return Entry::Occupied(OccupiedEntry { key: key.to_owned_key(), node })
+                }
+                Some(Some(c)) if node.children.contains_key(&c) => {
+                    let len = node.text.borrow().key_len();
+                    let Node { children, weight, max_len, .. } = node;
+                    ancestors.push(Ancestor { weight, max_len, at });
+                    at += len;
+                    node = children.get_mut(&c).unwrap();
+                }
+                // the key ends in the node without being a key, within its text or has no child to go on with,
+                // the visits are not counted as the nodes may move once it is inserted
+                _ => return vacant(Slot::Node { node, at }, ancestors),
+            }
+        }
+    }
+}
@@ -0,0 +1,31 @@
+use core::fmt::{Display, Formatter};
+
+/// errors returned by the fallible (`try_`) operations of the trie
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TrieError {
+    /// the operation needs a non empty prefix (the root of the trie is not a node)
+    EmptyPrefix,
+    /// the pattern has an unclosed or empty character class or a dangling escape at this char position
+    InvalidPattern { position: usize },
+    /// the regular expression is malformed or unsupported at this char position, nests too deeply or compiles to too many instructions
+    InvalidRegex { position: usize },
+    /// the data was written with a format version this crate cannot read
+    UnsupportedVersion { version: u32 },
+    /// the data is truncated, damaged or inconsistent
+    Corrupt { reason: &'static str },
+}
+
+impl Display for TrieError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            TrieError::EmptyPrefix => write!(f, "the prefix must not be empty"),
+            TrieError::InvalidPattern { position } => write!(f, "invalid pattern at position {}", position),
+            TrieError::InvalidRegex { position } => write!(f, "invalid regular expression at position {}", position),
+            TrieError::UnsupportedVersion { version } => write!(f, "unsupported format version {}", version),
+            TrieError::Corrupt { reason } => write!(f, "corrupt data: {}", reason),
+        }
+    }
+}
+
+impl core::error::Error for TrieError {}
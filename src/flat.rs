@@ -0,0 +1,62 @@
+//! serde helpers writing a trie as a plain map of its full keys to their values, in key order
+//!
+//! unlike the derived representation nothing about the nodes is written, so the output is readable
+//! by other tools and does not change when the layout of the nodes does. keys without a value map to
+//! `null`. a map read back is bulk inserted, a repeated key keeps its last value.
+//!
+//! ```
+//! use ab_radix_trie::Trie;
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct Dictionary {
+//!     #[serde(with = "ab_radix_trie::flat")]
+//!     words: Trie<u32>,
+//! }
+//!
+//! let mut words = Trie::new();
+//! words.insert("romulus", Some(2));
+//! words.insert("romanus", Some(1));
+//! words.insert("rubens", None);
+//! let json = serde_json::to_string(&Dictionary { words }).unwrap();
+//! assert_eq!(json, r#"{"words":{"romanus":1,"romulus":2,"rubens":null}}"#);
+//! let dictionary: Dictionary = serde_json::from_str(&json).unwrap();
+//! assert_eq!(dictionary.words.get("romulus"), Some(&2));
+//! ```
+//!
+//! a trie on its own goes through the functions directly, e.g. `flat::serialize(&trie, &mut serializer)`.
+use core::borrow::Borrow;
+use core::fmt::Formatter;
+use core::marker::PhantomData;
+use serde::de::{MapAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use crate::{Trie, TrieKey};
+
+pub fn serialize<V, K, S>(trie: &Trie<V, K>, serializer: S) -> Result<S::Ok, S::Error>
+where V: Serialize, K: ?Sized + TrieKey, K::Owned: Serialize, S: Serializer {
+    serializer.collect_map(trie.iter())
+}
+
+pub fn deserialize<'de, V, K, D>(deserializer: D) -> Result<Trie<V, K>, D::Error>
+where V: Deserialize<'de>, K: ?Sized + TrieKey, K::Owned: Deserialize<'de>, D: Deserializer<'de> {
+    deserializer.deserialize_map(FlatVisitor(PhantomData))
+}
+
+struct FlatVisitor<V, K: ?Sized + TrieKey>(PhantomData<fn() -> Trie<V, K>>);
+
+impl<'de, V, K> Visitor<'de> for FlatVisitor<V, K>
+where V: Deserialize<'de>, K: ?Sized + TrieKey, K::Owned: Deserialize<'de> {
+    type Value = Trie<V, K>;
+
+    fn expecting(&self, formatter: &mut Formatter) -> core::fmt::Result {
+        formatter.write_str("a map of keys to values")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut trie = Trie::new();
+        while let Some((key, value)) = map.next_entry::<K::Owned, Option<V>>()? {
+            trie.insert(key.borrow(), value);
+        }
+        Ok(trie)
+    }
+}
@@ -0,0 +1,377 @@
+//! an immutable trie flattened into a few contiguous arrays, see [`Trie::freeze`]
+//!
+//! the nodes are laid out breadth first, so the children of a node are a contiguous range of
+//! nodes (sorted by their first char, which each node keeps next to its offsets) and a lookup is a
+//! binary search per level over a handful of small records. the edge labels are concatenated into
+//! a single string and the values kept in the order of their nodes.
+use alloc::collections::VecDeque;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+use crate::map::CharMap;
+use crate::{Entry, MatchingOptions, Node, Offset, Tagged, Trie};
+
+/// marks a node which is not a key
+const NO_VALUE: u32 = u32::MAX;
+
+/// a node of a frozen trie, its label is `labels[label_start..label_start + label_len]`
+/// and its children are `nodes[first_child..first_child + child_count]`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct FrozenNode {
+    /// the first char of the label, what the children are sorted and searched by
+    pub(crate) first: char,
+    pub(crate) label_start: u32,
+    pub(crate) label_len: u32,
+    pub(crate) first_child: u32,
+    pub(crate) child_count: u32,
+    /// index into the values, `NO_VALUE` if the node is not a key
+    pub(crate) value: u32,
+}
+
+impl FrozenNode {
+    fn is_key(&self) -> bool {
+        self.value != NO_VALUE
+    }
+}
+
+/// an immutable trie built once with [`Trie::freeze`] and then only queried
+///
+/// ```
+/// use ab_radix_trie::Trie;
+/// let mut trie: Trie<i32> = Trie::new();
+/// trie.insert("romanus", Some(1));
+/// trie.insert("romulus", Some(2));
+/// trie.insert("rubens", None);
+/// let frozen = trie.freeze();
+/// assert_eq!(frozen.get("romulus"), Some(&2));
+/// assert!(frozen.contains_key("rubens"));
+/// assert_eq!(frozen.iter_prefix("rom").map(|(key, _)| key).collect::<Vec<_>>(), vec!["romanus", "romulus"]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct FrozenTrie<V> {
+    /// the edge labels of all the nodes, concatenated
+    pub(crate) labels: String,
+    /// breadth first, the root (with an empty label) comes first
+    pub(crate) nodes: Vec<FrozenNode>,
+    /// one slot per key, in the order of the nodes
+    pub(crate) values: Vec<Option<V>>,
+}
+
+/// converts an offset or a count, which the frozen layout keeps in 32 bits
+fn index(n: usize) -> u32 {
+    u32::try_from(n).expect("a frozen trie holds at most u32::MAX - 1 nodes, keys and label bytes")
+}
+
+/// read access to flattened nodes, shared by [`FrozenTrie`] and the in-place [`MappedTrie`](crate::image::MappedTrie)
+pub(crate) trait Layout {
+    fn node(&self, index: usize) -> FrozenNode;
+
+    fn label(&self, node: &FrozenNode) -> &str;
+
+    /// the index of the child starting with the char
+    fn child(&self, node: &FrozenNode, c: char) -> Option<usize> {
+        let (mut low, mut high) = (node.first_child as usize, node.first_child as usize + node.child_count as usize);
+        while low < high {
+            let mid = low + (high - low) / 2;
+            match self.node(mid).first.cmp(&c) {
+                Ordering::Less => low = mid + 1,
+                Ordering::Greater => high = mid,
+                Ordering::Equal => return Some(mid),
+            }
+        }
+        None
+    }
+
+    /// returns the node whose accumulated text is exactly the key (a key or not)
+    fn find_node(&self, key: &str) -> Option<FrozenNode> {
+        let mut node = self.node(0);
+        let mut rest = key;
+        while let Some(c) = rest.chars().next() {
+            node = self.node(self.child(&node, c)?);
+            rest = rest.strip_prefix(self.label(&node))?;
+        }
+        Some(node)
+    }
+
+    /// calls `on_key` with the length of every key which is a prefix of the input, shortest first
+    fn walk_prefixes(&self, input: &str, mut on_key: impl FnMut(usize, &FrozenNode)) {
+        let mut node = self.node(0);
+        let mut consumed = 0;
+        loop {
+            if node.is_key() {
+                on_key(consumed, &node);
+            }
+            let Some(child) = input[consumed..].chars().next().and_then(|c| self.child(&node, c)) else {
+                break
+            };
+            node = self.node(child);
+            let label = self.label(&node);
+            if !input[consumed..].starts_with(label) {
+                break
+            }
+            consumed += label.len();
+        }
+    }
+
+    /// a walk over the keys starting with the prefix
+    fn walk_prefix(&self, prefix: &str) -> Walk {
+        let mut node = self.node(0);
+        let mut consumed = 0;
+        // walks down until the rest of the prefix ends within the label of the node
+        while consumed < prefix.len() {
+            let rest = &prefix[consumed..];
+            let Some(child) = rest.chars().next().and_then(|c| self.child(&node, c)) else {
+                return Walk::empty()
+            };
+            node = self.node(child);
+            let label = self.label(&node);
+            if label.starts_with(rest) {
+                return Walk::new(child, String::from(&prefix[..consumed]))
+            }
+            if !rest.starts_with(label) {
+                return Walk::empty()
+            }
+            consumed += label.len();
+        }
+        // the empty prefix
+        Walk::new(0, String::new())
+    }
+}
+
+/// a depth first walk over the keys below a node in lexicographic order
+pub(crate) struct Walk {
+    /// nodes still to visit along with the length of the key leading up to them
+    stack: Vec<(usize, usize)>,
+    key: String,
+}
+
+impl Walk {
+    /// starts at the node, `key` is the text leading up to it
+    fn new(node: usize, key: String) -> Self {
+        Walk { stack: vec![(node, key.len())], key }
+    }
+
+    fn empty() -> Self {
+        Walk { stack: Vec::new(), key: String::new() }
+    }
+
+    /// the key of the node last returned by `next_key`
+    pub(crate) fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub(crate) fn next_key(&mut self, layout: &impl Layout) -> Option<FrozenNode> {
+        while let Some((node, len)) = self.stack.pop() {
+            let node = layout.node(node);
+            self.key.truncate(len);
+            self.key.push_str(layout.label(&node));
+            let len = self.key.len();
+            // largest first, so the smallest pops first
+            let first = node.first_child as usize;
+            self.stack.extend((first..first + node.child_count as usize).rev().map(|child| (child, len)));
+            if node.is_key() {
+                return Some(node)
+            }
+        }
+        None
+    }
+}
+
+impl<V> Layout for FrozenTrie<V> {
+    fn node(&self, index: usize) -> FrozenNode {
+        self.nodes[index]
+    }
+
+    fn label(&self, node: &FrozenNode) -> &str {
+        let start = node.label_start as usize;
+        &self.labels[start..start + node.label_len as usize]
+    }
+
+    fn child(&self, node: &FrozenNode, c: char) -> Option<usize> {
+        let index = self.children(node).binary_search_by_key(&c, |child| child.first).ok()?;
+        Some(node.first_child as usize + index)
+    }
+}
+
+impl<V> Trie<V> {
+    /// flattens the trie into an immutable [`FrozenTrie`] with the same keys and values
+    /// pending visits are dropped, a frozen trie does not track visits
+    ///
+    /// # Panics
+    /// if the trie has more than `u32::MAX - 1` nodes or keys, or more than 4GiB of text
+    pub fn freeze(self) -> FrozenTrie<V> {
+        let mut frozen = FrozenTrie {
+            labels: String::with_capacity(self.char_count),
+            nodes: Vec::with_capacity(self.node_count + 1),
+            values: Vec::with_capacity(self.key_count),
+        };
+        let root = frozen.push('\0', "", self.empty.map(|empty| empty.value));
+        let mut queue: VecDeque<(usize, CharMap<char, Node<V>>)> = VecDeque::from([(root, self.children)]);
+        while let Some((parent, children)) = queue.pop_front() {
+            frozen.nodes[parent].first_child = index(frozen.nodes.len());
+            frozen.nodes[parent].child_count = index(children.len());
+            for (first, child) in children {
+                let Node { text, terminal, children, value, .. } = child;
+                let node = frozen.push(first, &text, terminal.then_some(value));
+                queue.push_back((node, children));
+            }
+        }
+        frozen
+    }
+}
+
+impl<V> FrozenTrie<V> {
+    /// appends a node without children yet, `value` is None for a node which is not a key
+    fn push(&mut self, first: char, label: &str, value: Option<Option<V>>) -> usize {
+        let node = FrozenNode {
+            first,
+            label_start: index(self.labels.len()),
+            label_len: index(label.len()),
+            first_child: 0,
+            child_count: 0,
+            value: match value {
+                Some(value) => {
+                    self.values.push(value);
+                    index(self.values.len() - 1)
+                }
+                None => NO_VALUE,
+            },
+        };
+        self.labels.push_str(label);
+        self.nodes.push(node);
+        self.nodes.len() - 1
+    }
+
+    /// number of keys
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn children(&self, node: &FrozenNode) -> &[FrozenNode] {
+        let start = node.first_child as usize;
+        &self.nodes[start..start + node.child_count as usize]
+    }
+
+    fn value(&self, node: &FrozenNode) -> Option<&V> {
+        self.values.get(node.value as usize).and_then(Option::as_ref)
+    }
+
+    /// returns the value associated with the exact key
+    /// returns None if the key is not present or has no value
+    pub fn get(&self, key: &str) -> Option<&V> {
+        self.find_node(key).and_then(|node| self.value(&node))
+    }
+
+    /// true if the exact key is present (with or without a value)
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.find_node(key).is_some_and(|node| node.is_key())
+    }
+
+    /// returns the longest key which is a prefix of the input along with its value, see `Trie::longest_prefix_match`
+    pub fn longest_prefix_match<'k>(&self, input: &'k str) -> Option<(&'k str, Option<&V>)> {
+        self.prefixes_of(input).pop()
+    }
+
+    /// returns every key which is a prefix of the input (shortest first) along with its value
+    pub fn prefixes_of<'k>(&self, input: &'k str) -> Vec<(&'k str, Option<&V>)> {
+        let mut prefixes = Vec::new();
+        self.walk_prefixes(input, |consumed, node| prefixes.push((&input[..consumed], self.value(node))));
+        prefixes
+    }
+
+    /// iterates over all the keys and their values in lexicographic order
+    pub fn iter(&self) -> FrozenIter<'_, V> {
+        FrozenIter { trie: self, walk: Walk::new(0, String::new()) }
+    }
+
+    /// iterates over all the keys in lexicographic order
+    pub fn keys(&self) -> impl Iterator<Item = String> + '_ {
+        self.iter().map(|(key, _)| key)
+    }
+
+    /// iterates over the values in the order of their keys, keys without a value are skipped
+    pub fn values(&self) -> impl Iterator<Item = &V> {
+        self.iter().filter_map(|(_, value)| value)
+    }
+
+    /// iterates over the keys starting with the prefix (and their values) in lexicographic order
+    pub fn iter_prefix(&self, prefix: &str) -> FrozenIter<'_, V> {
+        FrozenIter { trie: self, walk: self.walk_prefix(prefix) }
+    }
+
+    /// returns every key (in full) matching the prefix under the matching options, in lexicographic order
+    /// see `Trie::get_keys_with_matching_options`
+    pub fn get_keys_with_matching_options(&self, prefix: &str, options: &MatchingOptions) -> Vec<Entry<'_, V>> {
+        let tagged = options.tag(prefix);
+        let mut collector = Vec::new();
+        self.collect_matching(&self.nodes[0], tagged.chars.as_slice(), options, &mut String::new(), &mut collector);
+        collector
+    }
+
+    fn collect_matching<'a>(&'a self, node: &FrozenNode, prefix: &[(Tagged, Offset)], options: &MatchingOptions,
+                            key: &mut String, collector: &mut Vec<Entry<'a, V>>) {
+        let label = self.label(node);
+        let mut rest = prefix;
+        for (tagged, _) in options.tag(label).chars.iter() {
+            let Some(((expected, _), remaining)) = rest.split_first() else {
+                break
+            };
+            if expected != tagged {
+                return
+            }
+            rest = remaining;
+        }
+        let len = key.len();
+        key.push_str(label);
+        if rest.is_empty() {
+            self.collect_all(node, key, collector);
+        } else {
+            for child in self.children(node) {
+                self.collect_matching(child, rest, options, key, collector);
+            }
+        }
+        key.truncate(len);
+    }
+
+    /// collects every key below the node in lexicographic order, `key` ends with the label of the node
+    fn collect_all<'a>(&'a self, node: &FrozenNode, key: &mut String, collector: &mut Vec<Entry<'a, V>>) {
+        if node.is_key() {
+            collector.push(Entry { key: key.clone(), val: &self.values[node.value as usize] });
+        }
+        let len = key.len();
+        for child in self.children(node) {
+            key.push_str(self.label(child));
+            self.collect_all(child, key, collector);
+            key.truncate(len);
+        }
+    }
+}
+
+/// iterator over `(key, value)` of a frozen trie, see [`FrozenTrie::iter`]
+pub struct FrozenIter<'a, V> {
+    trie: &'a FrozenTrie<V>,
+    walk: Walk,
+}
+
+impl<'a, V> Iterator for FrozenIter<'a, V> {
+    type Item = (String, Option<&'a V>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.walk.next_key(self.trie)?;
+        Some((String::from(self.walk.key()), self.trie.value(&node)))
+    }
+}
+
+impl<'a, V> IntoIterator for &'a FrozenTrie<V> {
+    type Item = (String, Option<&'a V>);
+    type IntoIter = FrozenIter<'a, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
@@ -0,0 +1,343 @@
+//! a versioned binary image of a frozen trie which is queried in place, see [`FrozenTrie::to_image`]
+//!
+//! the image is meant to be memory mapped (or read into a buffer once) and shared between processes:
+//! [`MappedTrie::new`] checks it once and the queries then read the node records straight out of the bytes.
+//! all the integers are little endian u32s, the layout is
+//!
+//! | bytes          | content                                                                         |
+//! |----------------|---------------------------------------------------------------------------------|
+//! | 8              | the magic `AB-TRIE\0`                                                           |
+//! | 4              | the format version                                                              |
+//! | 4              | the node count                                                                  |
+//! | 4              | the value count (the number of keys)                                            |
+//! | 4              | the label byte count                                                            |
+//! | 4              | the value byte count                                                            |
+//! | 4              | the CRC-32 of the whole image with this field zeroed                            |
+//! | 24 x nodes     | first char, label start, label length, first child, child count and value index |
+//! | 8 x values     | start and length in the value bytes, a start of `u32::MAX` for no value         |
+//! | labels         | the utf-8 labels, zero padded to a multiple of 4 bytes                          |
+//! | value bytes    | the values as encoded by the caller                                             |
+//!
+//! the nodes keep the breadth first layout of [`FrozenTrie`].
+use alloc::string::String;
+use alloc::vec::Vec;
+use crate::frozen::{FrozenNode, FrozenTrie, Layout, Walk};
+use crate::TrieError;
+
+const MAGIC: &[u8; 8] = b"AB-TRIE\0";
+/// the version written by `to_image`, images of any other version are rejected
+pub const VERSION: u32 = 1;
+const HEADER_LEN: usize = 32;
+const CHECKSUM_AT: usize = 28;
+const NODE_LEN: usize = 24;
+const VALUE_LEN: usize = 8;
+const NO_VALUE: u32 = u32::MAX;
+
+impl<V> FrozenTrie<V> {
+    /// writes the trie as a binary image which [`MappedTrie`] queries without deserializing it
+    /// `encode` appends the bytes of a value to the buffer
+    ///
+    /// ```
+    /// use ab_radix_trie::Trie;
+    /// use ab_radix_trie::image::MappedTrie;
+    /// let mut trie: Trie<u32> = Trie::new();
+    /// trie.insert("romanus", Some(1));
+    /// trie.insert("romulus", Some(2));
+    /// let image = trie.freeze().to_image(|value, out| out.extend_from_slice(&value.to_le_bytes()));
+    /// let mapped = MappedTrie::new(&image).unwrap();
+    /// assert_eq!(mapped.get("romulus"), Some(&2_u32.to_le_bytes()[..]));
+    /// ```
+    pub fn to_image(&self, mut encode: impl FnMut(&V, &mut Vec<u8>)) -> Vec<u8> {
+        let mut values = Vec::new();
+        let mut slots = Vec::with_capacity(self.values.len());
+        for value in &self.values {
+            slots.push(match value {
+                Some(value) => {
+                    let start = values.len();
+                    encode(value, &mut values);
+                    (index(start), index(values.len() - start))
+                }
+                None => (NO_VALUE, 0),
+            });
+        }
+        let padding = (4 - self.labels.len() % 4) % 4;
+        let mut image = Vec::with_capacity(HEADER_LEN + self.nodes.len() * NODE_LEN + slots.len() * VALUE_LEN
+            + self.labels.len() + padding + values.len());
+        image.extend_from_slice(MAGIC);
+        for n in [VERSION, index(self.nodes.len()), index(slots.len()), index(self.labels.len()), index(values.len()), 0] {
+            image.extend_from_slice(&n.to_le_bytes());
+        }
+        for node in &self.nodes {
+            for n in [node.first as u32, node.label_start, node.label_len, node.first_child, node.child_count, node.value] {
+                image.extend_from_slice(&n.to_le_bytes());
+            }
+        }
+        for (start, len) in slots {
+            image.extend_from_slice(&start.to_le_bytes());
+            image.extend_from_slice(&len.to_le_bytes());
+        }
+        image.extend_from_slice(self.labels.as_bytes());
+        image.resize(image.len() + padding, 0);
+        image.extend_from_slice(&values);
+        let checksum = crc32(&image);
+        image[CHECKSUM_AT..HEADER_LEN].copy_from_slice(&checksum.to_le_bytes());
+        image
+    }
+}
+
+fn index(n: usize) -> u32 {
+    u32::try_from(n).expect("an image holds at most 4GiB of values")
+}
+
+/// a frozen trie queried in place over the bytes of an image, see [`FrozenTrie::to_image`]
+/// values are returned as the bytes they were encoded to
+#[derive(Debug, Clone, Copy)]
+pub struct MappedTrie<'a> {
+    bytes: &'a [u8],
+    labels: &'a str,
+    node_count: usize,
+    value_count: usize,
+    /// where the value slots and the value bytes start
+    slots_at: usize,
+    values_at: usize,
+}
+
+fn read_u32(bytes: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]])
+}
+
+fn corrupt(reason: &'static str) -> TrieError {
+    TrieError::Corrupt { reason }
+}
+
+impl<'a> MappedTrie<'a> {
+    /// checks the magic, the version, the checksum and the structure of the image
+    /// once it returns Ok no query reads outside the image or panics
+    pub fn new(bytes: &'a [u8]) -> Result<Self, TrieError> {
+        if bytes.len() < HEADER_LEN || &bytes[..MAGIC.len()] != MAGIC {
+            return Err(corrupt("not a trie image"))
+        }
+        let version = read_u32(bytes, 8);
+        if version != VERSION {
+            return Err(TrieError::UnsupportedVersion { version })
+        }
+        let [node_count, value_count, label_len, value_len] = [12, 16, 20, 24].map(|at| read_u32(bytes, at) as usize);
+        let slots_at = node_count.checked_mul(NODE_LEN).and_then(|n| n.checked_add(HEADER_LEN));
+        let labels_at = value_count.checked_mul(VALUE_LEN).and_then(|n| n.checked_add(slots_at?));
+        let values_at = labels_at.and_then(|n| n.checked_add(label_len.next_multiple_of(4)));
+        let (Some(slots_at), Some(labels_at), Some(values_at)) = (slots_at, labels_at, values_at) else {
+            return Err(corrupt("sections overflow"))
+        };
+        if values_at.checked_add(value_len) != Some(bytes.len()) {
+            return Err(corrupt("length does not match the header"))
+        }
+        let mut crc = Crc32::new();
+        crc.update(&bytes[..CHECKSUM_AT]);
+        crc.update(&[0; 4]);
+        crc.update(&bytes[HEADER_LEN..]);
+        if crc.finish() != read_u32(bytes, CHECKSUM_AT) {
+            return Err(corrupt("checksum mismatch"))
+        }
+        let labels = core::str::from_utf8(&bytes[labels_at..labels_at + label_len])
+            .map_err(|_| corrupt("labels are not utf-8"))?;
+        if bytes[labels_at + label_len..values_at].iter().any(|b| *b != 0) {
+            return Err(corrupt("label padding is not zero"))
+        }
+        let trie = MappedTrie { bytes, labels, node_count, value_count, slots_at, values_at };
+        trie.check_structure()?;
+        Ok(trie)
+    }
+
+    /// checks the records so walking the nodes stays in bounds and terminates
+    fn check_structure(&self) -> Result<(), TrieError> {
+        if self.node_count == 0 {
+            return Err(corrupt("no root node"))
+        }
+        let mut used = alloc::vec![false; self.value_count];
+        // breadth first, the children of the nodes are consecutive ranges right after the root
+        let mut next_child = 1;
+        for index in 0..self.node_count {
+            let at = HEADER_LEN + index * NODE_LEN;
+            let first = char::from_u32(read_u32(self.bytes, at)).ok_or(corrupt("invalid first char"))?;
+            let [label_start, label_len, first_child, child_count, value] = [4, 8, 12, 16, 20]
+                .map(|offset| read_u32(self.bytes, at + offset) as usize);
+            let label = label_start.checked_add(label_len).and_then(|end| self.labels.get(label_start..end))
+                .ok_or(corrupt("label out of bounds"))?;
+            if index == 0 && !label.is_empty() {
+                return Err(corrupt("the root has a label"))
+            }
+            if index > 0 && !label.starts_with(first) {
+                return Err(corrupt("first char does not match the label"))
+            }
+            // every node but the root is in the child range of an earlier node, so no range points back
+            if index > 0 && next_child <= index {
+                return Err(corrupt("unreachable nodes or values"))
+            }
+            if first_child != next_child || child_count > self.node_count - next_child {
+                return Err(corrupt("children out of place"))
+            }
+            next_child += child_count;
+            // the first chars of neighbouring children are compared in place
+            let first_char = |child: usize| read_u32(self.bytes, HEADER_LEN + child * NODE_LEN);
+            if (first_child + 1..first_child + child_count).any(|child| first_char(child - 1) >= first_char(child)) {
+                return Err(corrupt("children are not sorted"))
+            }
+            if value as u32 != NO_VALUE {
+                match used.get_mut(value) {
+                    Some(used) if !*used => *used = true,
+                    _ => return Err(corrupt("value index out of bounds or shared")),
+                }
+            }
+        }
+        if next_child != self.node_count || used.iter().any(|used| !used) {
+            return Err(corrupt("unreachable nodes or values"))
+        }
+        let value_len = self.bytes.len() - self.values_at;
+        for slot in 0..self.value_count {
+            let at = self.slots_at + slot * VALUE_LEN;
+            let (start, len) = (read_u32(self.bytes, at), read_u32(self.bytes, at + 4) as usize);
+            let valid = if start == NO_VALUE { len == 0 } else { (start as usize).checked_add(len).is_some_and(|end| end <= value_len) };
+            if !valid {
+                return Err(corrupt("value out of bounds"))
+            }
+        }
+        Ok(())
+    }
+
+    /// number of keys
+    pub fn len(&self) -> usize {
+        self.value_count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn value(&self, node: &FrozenNode) -> Option<&'a [u8]> {
+        if node.value == NO_VALUE {
+            return None
+        }
+        let at = self.slots_at + node.value as usize * VALUE_LEN;
+        let start = read_u32(self.bytes, at);
+        if start == NO_VALUE {
+            return None
+        }
+        let start = self.values_at + start as usize;
+        Some(&self.bytes[start..start + read_u32(self.bytes, at + 4) as usize])
+    }
+
+    /// returns the encoded value associated with the exact key
+    /// returns None if the key is not present or has no value
+    pub fn get(&self, key: &str) -> Option<&'a [u8]> {
+        self.find_node(key).and_then(|node| self.value(&node))
+    }
+
+    /// true if the exact key is present (with or without a value)
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.find_node(key).is_some_and(|node| node.value != NO_VALUE)
+    }
+
+    /// returns the longest key which is a prefix of the input along with its value, see `Trie::longest_prefix_match`
+    pub fn longest_prefix_match<'k>(&self, input: &'k str) -> Option<(&'k str, Option<&'a [u8]>)> {
+        self.prefixes_of(input).pop()
+    }
+
+    /// returns every key which is a prefix of the input (shortest first) along with its value
+    pub fn prefixes_of<'k>(&self, input: &'k str) -> Vec<(&'k str, Option<&'a [u8]>)> {
+        let mut prefixes = Vec::new();
+        self.walk_prefixes(input, |consumed, node| prefixes.push((&input[..consumed], self.value(node))));
+        prefixes
+    }
+
+    /// iterates over all the keys and their values in lexicographic order
+    pub fn iter(&self) -> MappedIter<'a> {
+        self.iter_prefix("")
+    }
+
+    /// iterates over all the keys in lexicographic order
+    pub fn keys(&self) -> impl Iterator<Item = String> + 'a {
+        self.iter().map(|(key, _)| key)
+    }
+
+    /// iterates over the keys starting with the prefix (and their values) in lexicographic order
+    pub fn iter_prefix(&self, prefix: &str) -> MappedIter<'a> {
+        MappedIter { trie: *self, walk: self.walk_prefix(prefix) }
+    }
+}
+
+impl Layout for MappedTrie<'_> {
+    fn node(&self, index: usize) -> FrozenNode {
+        let at = HEADER_LEN + index * NODE_LEN;
+        let [first, label_start, label_len, first_child, child_count, value] = [0, 4, 8, 12, 16, 20]
+            .map(|offset| read_u32(self.bytes, at + offset));
+        FrozenNode {
+            first: char::from_u32(first).expect("checked on load"),
+            label_start,
+            label_len,
+            first_child,
+            child_count,
+            value,
+        }
+    }
+
+    fn label(&self, node: &FrozenNode) -> &str {
+        let start = node.label_start as usize;
+        &self.labels[start..start + node.label_len as usize]
+    }
+}
+
+/// iterator over `(key, encoded value)` of an image, see [`MappedTrie::iter`]
+pub struct MappedIter<'a> {
+    trie: MappedTrie<'a>,
+    walk: Walk,
+}
+
+impl<'a> Iterator for MappedIter<'a> {
+    type Item = (String, Option<&'a [u8]>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.walk.next_key(&self.trie)?;
+        Some((String::from(self.walk.key()), self.trie.value(&node)))
+    }
+}
+
+/// CRC-32 (IEEE), computed a byte at a time from a table
+pub(crate) struct Crc32(u32);
+
+const CRC_TABLE: [u32; 256] = {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 { (crc >> 1) ^ 0xedb8_8320 } else { crc >> 1 };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+impl Crc32 {
+    pub(crate) fn new() -> Self {
+        Crc32(u32::MAX)
+    }
+
+    pub(crate) fn update(&mut self, bytes: &[u8]) {
+        for b in bytes {
+            self.0 = CRC_TABLE[((self.0 ^ *b as u32) & 0xff) as usize] ^ (self.0 >> 8);
+        }
+    }
+
+    pub(crate) fn finish(&self) -> u32 {
+        !self.0
+    }
+}
+
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = Crc32::new();
+    crc.update(bytes);
+    crc.finish()
+}
@@ -0,0 +1,220 @@
+//! lazy iterators over the keys and values of a trie
+//!
+//! keys are produced in lexicographic order (by unicode scalar value) from an explicit stack,
+//! so nothing is collected up front besides the children of the node being expanded.
+use alloc::vec;
+use alloc::vec::Vec;
+use core::borrow::Borrow;
+use crate::map::CharMap;
+use crate::rank::MaxValue;
+use crate::{Node, Trie, TrieKey};
+
+/// children of a node in the order they should be pushed onto the stack (largest first, so the smallest pops first)
+pub(crate) fn stack_order<V, K: ?Sized + TrieKey>(children: &CharMap<K::Symbol, Node<V, K>>) -> Vec<&Node<V, K>> {
+    children.values().rev().collect()
+}
+
+/// iterator over `(key, value)` of a trie, see [`Trie::iter`]
+pub struct Iter<'a, V, K: ?Sized + TrieKey = str> {
+    /// nodes still to visit along with the length of the key leading up to them
+    stack: Vec<(&'a Node<V, K>, usize)>,
+    key: K::Owned,
+}
+
+impl<'a, V, K: ?Sized + TrieKey> Iter<'a, V, K> {
+    pub(crate) fn new(key: K::Owned, roots: Vec<&'a Node<V, K>>) -> Self {
+        let len = key.borrow().key_len();
+        Self {
+            stack: roots.into_iter().map(|node| (node, len)).collect(),
+            key,
+        }
+    }
+}
+
+impl<'a, V, K: ?Sized + TrieKey> Iterator for Iter<'a, V, K> {
+    type Item = (K::Owned, Option<&'a V>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((node, len)) = self.stack.pop() {
+            K::truncate_key(&mut self.key, len);
+            K::push_key(&mut self.key, node.text.borrow());
+            let len = self.key.borrow().key_len();
+            self.stack.extend(stack_order(&node.children).into_iter().map(|child| (child, len)));
+            if node.terminal {
+                return Some((self.key.clone(), node.value.as_ref()))
+            }
+        }
+        None
+    }
+}
+
+/// iterator over `(key, mutable value)` of a trie, see [`Trie::iter_mut`]
+pub struct IterMut<'a, V, K: ?Sized + TrieKey = str> {
+    stack: Vec<(&'a mut Node<V, K>, usize)>,
+    key: K::Owned,
+}
+
+impl<'a, V, K: ?Sized + TrieKey> Iterator for IterMut<'a, V, K> {
+    type Item = (K::Owned, Option<&'a mut V>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((node, len)) = self.stack.pop() {
+            let Node { text, terminal, children, value, max_value_at, .. } = node;
+            // the value may be changed, the nodes above were marked when they were visited
+            *max_value_at = MaxValue::Stale;
+            K::truncate_key(&mut self.key, len);
+            K::push_key(&mut self.key, (*text).borrow());
+            let len = self.key.borrow().key_len();
+            self.stack.extend(children.values_mut().rev().map(|child| (child, len)));
+            if *terminal {
+                return Some((self.key.clone(), value.as_mut()))
+            }
+        }
+        None
+    }
+}
+
+/// owning iterator over `(key, value)` of a trie
+pub struct IntoIter<V, K: ?Sized + TrieKey = str> {
+    stack: Vec<(Node<V, K>, usize)>,
+    key: K::Owned,
+}
+
+impl<V, K: ?Sized + TrieKey> Iterator for IntoIter<V, K> {
+    type Item = (K::Owned, Option<V>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((node, len)) = self.stack.pop() {
+            let Node { text, terminal, children, value, .. } = node;
+            K::truncate_key(&mut self.key, len);
+            K::push_key(&mut self.key, text.borrow());
+            let len = self.key.borrow().key_len();
+            self.stack.extend(children.into_values().rev().map(|child| (child, len)));
+            if terminal {
+                return Some((self.key.clone(), value))
+            }
+        }
+        None
+    }
+}
+
+/// iterator over the keys of a trie, see [`Trie::keys`]
+pub struct Keys<'a, V, K: ?Sized + TrieKey = str> {
+    inner: Iter<'a, V, K>,
+}
+
+impl<'a, V, K: ?Sized + TrieKey> Iterator for Keys<'a, V, K> {
+    type Item = K::Owned;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(key, _)| key)
+    }
+}
+
+/// iterator over the values of a trie, see [`Trie::values`]
+pub struct Values<'a, V, K: ?Sized + TrieKey = str> {
+    inner: Iter<'a, V, K>,
+}
+
+impl<'a, V, K: ?Sized + TrieKey> Iterator for Values<'a, V, K> {
+    type Item = &'a V;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.by_ref().find_map(|(_, value)| value)
+    }
+}
+
+impl<V, K: ?Sized + TrieKey> Trie<V, K> {
+    /// iterates over all the keys and their values in lexicographic order
+    /// keys inserted without a value are yielded with `None`
+    pub fn iter(&self) -> Iter<'_, V, K> {
+        let mut roots = stack_order(&self.children);
+        // the empty key comes before everything else
+        roots.extend(self.empty.as_ref());
+        Iter::new(K::Owned::default(), roots)
+    }
+
+    /// like `iter` but with mutable access to the values
+    pub fn iter_mut(&mut self) -> IterMut<'_, V, K> {
+        let mut stack = self.children.values_mut().rev().map(|node| (node, 0)).collect::<Vec<_>>();
+        stack.extend(self.empty.as_mut().map(|node| (node, 0)));
+        IterMut {
+            stack,
+            key: K::Owned::default(),
+        }
+    }
+
+    /// all the keys in lexicographic order
+    pub fn keys(&self) -> Keys<'_, V, K> {
+        Keys { inner: self.iter() }
+    }
+
+    /// all the values, ordered by their keys (keys without a value are skipped)
+    pub fn values(&self) -> Values<'_, V, K> {
+        Values { inner: self.iter() }
+    }
+
+    /// iterates over the keys starting with the prefix (including the prefix itself) in lexicographic order
+    /// unlike `get_suffixes_values` the full keys are returned
+    pub fn iter_prefix(&self, prefix: &K) -> Iter<'_, V, K> {
+        if prefix.key_len() == 0 {
+            return self.iter()
+        }
+        match self.prefix_root(prefix) {
+            Some((consumed, node)) => Iter::new(prefix.split_key_at(consumed).0.to_owned_key(), vec![node]),
+            None => Iter::new(K::Owned::default(), vec![]),
+        }
+    }
+
+    /// the topmost node whose subtree holds all the keys starting with the (non empty) prefix
+    /// along with the length (in units) of the key leading up to that node
+    pub(crate) fn prefix_root(&self, prefix: &K) -> Option<(usize, &Node<V, K>)> {
+        let mut node = self.children.get(&prefix.first_symbol()?);
+        let mut consumed = 0;
+        while let Some(current) = node {
+            let remaining = prefix.split_key_at(consumed).1;
+            let text: &K = current.text.borrow();
+            if text.strip_key_prefix(remaining).is_some() {
+                return Some((consumed, current))
+            }
+            if remaining.strip_key_prefix(text).is_none() {
+                break
+            }
+            consumed += text.key_len();
+            node = prefix.split_key_at(consumed).1.first_symbol().and_then(|c| current.children.get(&c));
+        }
+        None
+    }
+}
+
+impl<'a, V, K: ?Sized + TrieKey> IntoIterator for &'a Trie<V, K> {
+    type Item = (K::Owned, Option<&'a V>);
+    type IntoIter = Iter<'a, V, K>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, V, K: ?Sized + TrieKey> IntoIterator for &'a mut Trie<V, K> {
+    type Item = (K::Owned, Option<&'a mut V>);
+    type IntoIter = IterMut<'a, V, K>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+impl<V, K: ?Sized + TrieKey> IntoIterator for Trie<V, K> {
+    type Item = (K::Owned, Option<V>);
+    type IntoIter = IntoIter<V, K>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        let mut stack = self.children.into_values().rev().map(|node| (node, 0)).collect::<Vec<_>>();
+        stack.extend(self.empty.map(|node| (node, 0)));
+        IntoIter {
+            stack,
+            key: K::Owned::default(),
+        }
+    }
+}
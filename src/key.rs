@@ -0,0 +1,155 @@
+//! the key types a trie can be built over
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::borrow::Borrow;
+use core::fmt::Debug;
+use core::hash::Hash;
+
+/// a key of a [`Trie`](crate::Trie): a sequence of symbols which the nodes split between them
+///
+/// implemented for `str` (the default, with `char` symbols) and for slices `[T]` of any
+/// `Clone + Eq + Hash + Ord + Debug` symbols, e.g. `[u8]` for binary keys, `[u32]` for token ids or
+/// `[&str]` for word sequences. the symbols are ordered so the keys can be iterated in order.
+///
+/// keys are measured in units, the offsets at which they can be split: bytes for `str` (always on
+/// a char boundary) and symbols for slices.
+///
+/// ```
+/// use ab_radix_trie::Trie;
+/// let mut trie: Trie<u32, [u8]> = Trie::new();
+/// trie.insert(b"\x7fELF", Some(1));
+/// trie.insert(b"\x89PNG", Some(2));
+/// assert_eq!(trie.get(b"\x89PNG"), Some(&2));
+/// assert_eq!(trie.keys().next(), Some(b"\x7fELF".to_vec()));
+/// ```
+pub trait TrieKey {
+    /// the symbol on the edge to a child, i.e. the first symbol of its text
+    type Symbol: Clone + Eq + Hash + Ord + Debug;
+    /// the owned form of the key, the text of a node and the keys returned by the iterators
+    type Owned: Clone + Default + Debug + Borrow<Self>;
+
+    fn to_owned_key(&self) -> Self::Owned;
+
+    fn first_symbol(&self) -> Option<Self::Symbol>;
+
+    /// the length in units
+    fn key_len(&self) -> usize;
+
+    /// the number of symbols, the same as `key_len` unless a symbol spans several units
+    fn symbol_count(&self) -> usize;
+
+    /// the length in units of the longest common prefix of both keys
+    fn common_prefix_len(&self, other: &Self) -> usize;
+
+    /// splits the key at a unit offset on a symbol boundary
+    fn split_key_at(&self, at: usize) -> (&Self, &Self);
+
+    fn push_key(owned: &mut Self::Owned, key: &Self);
+
+    /// shortens the owned key to `len` units
+    fn truncate_key(owned: &mut Self::Owned, len: usize);
+
+    /// splits the owned key at a unit offset, returning everything after it
+    fn split_off_key(owned: &mut Self::Owned, at: usize) -> Self::Owned;
+
+    /// the rest of the key after the prefix, None if the key does not start with it
+    fn strip_key_prefix(&self, prefix: &Self) -> Option<&Self> {
+        let len = prefix.key_len();
+        (self.common_prefix_len(prefix) == len).then(|| self.split_key_at(len).1)
+    }
+}
+
+impl TrieKey for str {
+    type Symbol = char;
+    type Owned = String;
+
+    fn to_owned_key(&self) -> String {
+        String::from(self)
+    }
+
+    fn first_symbol(&self) -> Option<char> {
+        self.chars().next()
+    }
+
+    fn key_len(&self) -> usize {
+        self.len()
+    }
+
+    fn symbol_count(&self) -> usize {
+        self.chars().count()
+    }
+
+    /// always on a char boundary
+    fn common_prefix_len(&self, other: &str) -> usize {
+        self.char_indices()
+            .zip(other.chars())
+            .find(|((_, x), y)| x != y)
+            .map(|((i, _), _)| i)
+            .unwrap_or_else(|| self.len().min(other.len()))
+    }
+
+    fn split_key_at(&self, at: usize) -> (&str, &str) {
+        self.split_at(at)
+    }
+
+    fn push_key(owned: &mut String, key: &str) {
+        owned.push_str(key);
+    }
+
+    fn truncate_key(owned: &mut String, len: usize) {
+        owned.truncate(len);
+    }
+
+    fn split_off_key(owned: &mut String, at: usize) -> String {
+        owned.split_off(at)
+    }
+
+    fn strip_key_prefix(&self, prefix: &str) -> Option<&str> {
+        self.strip_prefix(prefix)
+    }
+}
+
+impl<T: Clone + Eq + Hash + Ord + Debug> TrieKey for [T] {
+    type Symbol = T;
+    type Owned = Vec<T>;
+
+    fn to_owned_key(&self) -> Vec<T> {
+        self.to_vec()
+    }
+
+    fn first_symbol(&self) -> Option<T> {
+        self.first().cloned()
+    }
+
+    fn key_len(&self) -> usize {
+        self.len()
+    }
+
+    fn symbol_count(&self) -> usize {
+        self.len()
+    }
+
+    fn common_prefix_len(&self, other: &[T]) -> usize {
+        self.iter().zip(other).take_while(|(x, y)| x == y).count()
+    }
+
+    fn split_key_at(&self, at: usize) -> (&[T], &[T]) {
+        self.split_at(at)
+    }
+
+    fn push_key(owned: &mut Vec<T>, key: &[T]) {
+        owned.extend_from_slice(key);
+    }
+
+    fn truncate_key(owned: &mut Vec<T>, len: usize) {
+        owned.truncate(len);
+    }
+
+    fn split_off_key(owned: &mut Vec<T>, at: usize) -> Vec<T> {
+        owned.split_off(at)
+    }
+
+    fn strip_key_prefix(&self, prefix: &[T]) -> Option<&[T]> {
+        self.strip_prefix(prefix)
+    }
+}
@@ -0,0 +1,121 @@
+//! typo tolerant search by edit distance (insertions, deletions and substitutions of chars)
+//!
+//! the query is matched against the edges with an incremental levenshtein row per char of the
+//! edge, so a prefix shared by many keys is only computed once. once every entry of the row is
+//! above the bound no key below can match and the subtree is skipped.
+use alloc::string::String;
+use alloc::vec::Vec;
+use crate::iter::stack_order;
+use crate::{Node, Trie};
+
+/// a key found by [`Trie::fuzzy_search`] or [`Trie::fuzzy_prefix_search`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct FuzzyMatch<'a, V> {
+    pub key: String,
+    pub value: Option<&'a V>,
+    /// the edit distance between the query and the key (or its closest prefix in prefix mode)
+    pub distance: usize,
+}
+
+struct Search<'a, V> {
+    query: Vec<char>,
+    max_distance: usize,
+    /// the query may match any prefix of a key instead of the whole key
+    prefix: bool,
+    key: String,
+    hits: Vec<FuzzyMatch<'a, V>>,
+}
+
+impl<'a, V> Search<'a, V> {
+    /// the row after matching one more char of the key
+    fn next_row(&self, row: &[usize], c: char) -> Vec<usize> {
+        let mut next = Vec::with_capacity(row.len());
+        next.push(row[0] + 1);
+        for (i, q) in self.query.iter().enumerate() {
+            let substitution = row[i] + usize::from(*q != c);
+            next.push(substitution.min(row[i + 1] + 1).min(next[i] + 1));
+        }
+        next
+    }
+
+    /// `row` is None once the distance of every key below is settled by `matched` (prefix mode)
+    /// `matched` is the smallest distance of a prefix of the key so far (prefix mode)
+    fn walk(&mut self, node: &'a Node<V>, row: Option<&[usize]>, mut matched: Option<usize>) {
+        let len = self.key.len();
+        self.key.push_str(node.text.as_str());
+        let mut row = row.map(|row| row.to_vec());
+        for c in node.text.chars() {
+            let Some(current) = &row else {
+                break
+            };
+            let next = self.next_row(current, c);
+            let last = next[self.query.len()];
+            if self.prefix && last <= self.max_distance {
+                matched = Some(matched.map_or(last, |m| m.min(last)));
+            }
+            row = if next.iter().min().is_some_and(|min| *min > self.max_distance) {
+                if matched.is_none() {
+                    // nothing below can get back under the bound
+                    self.key.truncate(len);
+                    return
+                }
+                None
+            } else {
+                Some(next)
+            };
+        }
+        if node.terminal {
+            let distance = match &row {
+                _ if self.prefix => matched,
+                Some(row) => Some(row[self.query.len()]).filter(|d| *d <= self.max_distance),
+                None => None,
+            };
+            if let Some(distance) = distance {
+                self.hits.push(FuzzyMatch { key: self.key.clone(), value: node.value.as_ref(), distance });
+            }
+        }
+        for child in stack_order(&node.children).into_iter().rev() {
+            self.walk(child, row.as_deref(), matched);
+        }
+        self.key.truncate(len);
+    }
+}
+
+impl<V> Trie<V> {
+    /// returns the keys within `max_distance` edits of the query, closest first (then in lexicographic order)
+    /// ```
+    /// use ab_radix_trie::Trie;
+    /// let mut trie: Trie<()> = Trie::new();
+    /// trie.insert("rubicon", None);
+    /// trie.insert("rubens", None);
+    /// let hits = trie.fuzzy_search("rubicom", 1);
+    /// assert_eq!(hits.len(), 1);
+    /// assert_eq!((hits[0].key.as_str(), hits[0].distance), ("rubicon", 1));
+    /// ```
+    pub fn fuzzy_search(&self, query: &str, max_distance: usize) -> Vec<FuzzyMatch<'_, V>> {
+        self.levenshtein(query, max_distance, false)
+    }
+
+    /// like `fuzzy_search` but the query only has to match the start of a key (for autocomplete)
+    /// the distance of a key is the one of its closest prefix, e.g. "rubcon" matches "rubicon" and "rubiconia" with 1
+    pub fn fuzzy_prefix_search(&self, query: &str, max_distance: usize) -> Vec<FuzzyMatch<'_, V>> {
+        self.levenshtein(query, max_distance, true)
+    }
+
+    fn levenshtein(&self, query: &str, max_distance: usize, prefix: bool) -> Vec<FuzzyMatch<'_, V>> {
+        let query = query.chars().collect::<Vec<_>>();
+        // the distance of the empty string to every prefix of the query
+        let first_row = (0..=query.len()).collect::<Vec<_>>();
+        let matched = Some(query.len()).filter(|d| prefix && *d <= max_distance);
+        let mut search = Search { query, max_distance, prefix, key: String::new(), hits: Vec::new() };
+        if let Some(empty) = &self.empty {
+            search.walk(empty, Some(first_row.as_slice()), matched);
+        }
+        for child in stack_order(&self.children).into_iter().rev() {
+            search.walk(child, Some(first_row.as_slice()), matched);
+        }
+        // stable, so keys with the same distance stay in lexicographic order
+        search.hits.sort_by_key(|hit| hit.distance);
+        search.hits
+    }
+}
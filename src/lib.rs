@@ -1,140 +1,524 @@
-use std::collections::{HashMap, HashSet};
-use std::collections::hash_map::DefaultHasher;
-use std::fmt::{Debug, Formatter};
-use std::hash::{Hash, Hasher};
-use std::sync::atomic::Ordering::Relaxed;
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+extern crate alloc;
+
+use alloc::collections::BTreeSet;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::borrow::Borrow;
+use core::cmp::Ordering;
+use core::fmt::{Debug, Formatter};
+use core::hash::{Hash, Hasher};
+#[cfg(feature = "std")]
+use std::collections::HashSet;
+use map::{CharMap, Entry as CharMapEntry};
+use rank::MaxValue;
+use visits::{VisitTracker, Visits};
+#[cfg(feature = "serde")]
 use serde::Serialize;
+#[cfg(feature = "serde")]
 use serde::Deserialize;
+#[cfg(feature = "tracing")]
 use log::trace;
-#[derive(Debug,  Serialize, Deserialize)]
-pub struct Trie<V> {
-    children: HashMap<char, Node<V>>,
-    #[serde(default)]
-    node_count: std::sync::atomic::AtomicU32, // TODO: this didn't need to be atomic - had mutability issues to contend with
+
+pub mod entry;
+mod automaton;
+#[cfg(feature = "std")]
+pub mod codec;
+mod error;
+#[cfg(feature = "serde")]
+pub mod flat;
+pub mod frozen;
+pub mod image;
+pub mod iter;
+mod key;
+pub mod levenshtein;
+mod map;
+pub mod pattern;
+pub mod rank;
+pub mod regex;
+mod validate;
+mod visits;
+
+pub use error::TrieError;
+pub use key::TrieKey;
+pub use validate::InvariantViolation;
+
+/// a compressed trie mapping keys (strings by default, see [`TrieKey`] for the others) to optional values
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(
+    from = "SerializedTrie<V, K>",
+    bound(
+        serialize = "V: Serialize, K::Owned: Serialize, K::Symbol: Serialize",
+        deserialize = "V: Deserialize<'de>, K::Owned: Deserialize<'de>, K::Symbol: Deserialize<'de>",
+    ),
+))]
+pub struct Trie<V, K: ?Sized + TrieKey = str> {
+    children: CharMap<K::Symbol, Node<V, K>>,
+    node_count: usize,
+    char_count: usize,
+    key_count: usize,
+    /// the empty key is stored on the root level as a node without text
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    empty: Option<Node<V, K>>,
+    /// pending visits of read only queries, only present while visit tracking is on (see `track_visits`)
+    #[cfg_attr(feature = "serde", serde(skip))]
+    visit_tracker: Option<VisitTracker>,
+    /// orders the values while the largest value of every subtree is kept (see `track_max_values`)
+    #[cfg_attr(feature = "serde", serde(skip))]
+    value_order: Option<fn(&V, &V) -> Ordering>,
+}
+
+/// the serialized fields of a trie, the counters are recomputed from the nodes
+/// (older versions did not write all of them, and they are not trusted either way)
+#[cfg(feature = "serde")]
+#[derive(Deserialize)]
+#[serde(bound = "V: Deserialize<'de>, K::Owned: Deserialize<'de>, K::Symbol: Deserialize<'de>")]
+struct SerializedTrie<V, K: ?Sized + TrieKey> {
+    children: CharMap<K::Symbol, Node<V, K>>,
     #[serde(default)]
-    char_count: std::sync::atomic::AtomicU32,
+    empty: Option<Node<V, K>>,
 }
 
-impl <V:Clone> Clone for Trie<V> {
+#[cfg(feature = "serde")]
+impl<V, K: ?Sized + TrieKey> From<SerializedTrie<V, K>> for Trie<V, K> {
+    fn from(trie: SerializedTrie<V, K>) -> Self {
+        let mut node_count = 0;
+        let mut char_count = 0;
+        let mut key_count = trie.empty.is_some() as usize;
+        let mut stack = trie.children.values().collect::<Vec<_>>();
+        while let Some(node) = stack.pop() {
+            node_count += 1;
+            char_count += node.text.borrow().key_len();
+            key_count += node.terminal as usize;
+            stack.extend(node.children.values());
+        }
+        Trie {
+            children: trie.children,
+            node_count,
+            char_count,
+            key_count,
+            empty: trie.empty,
+            visit_tracker: None,
+            value_order: None,
+        }
+    }
+}
+
+/// a clone starts without the visits that were not merged yet (see `Trie::merge_visits`)
+impl<V: Clone, K: ?Sized + TrieKey> Clone for Trie<V, K> {
     fn clone(&self) -> Self {
-        let x = self.node_count.load(Relaxed);
         Self { children: self.children.clone(),
-            node_count: std::sync::atomic::AtomicU32::new(x),
-            char_count: std::sync::atomic::AtomicU32::new(self.char_count.load(Relaxed)) }
+            node_count: self.node_count,
+            char_count: self.char_count,
+            key_count: self.key_count,
+            empty: self.empty.clone(),
+            #[cfg(feature = "std")]
+            visit_tracker: self.visit_tracker.as_ref().map(|_| VisitTracker::new()),
+            #[cfg(not(feature = "std"))]
+            visit_tracker: None,
+            value_order: self.value_order }
     }
 }
-    
 
-impl<V> Trie<V> {
-    pub fn new() -> Trie<V> {
+impl<V, K: ?Sized + TrieKey> Default for Trie<V, K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<V, K: ?Sized + TrieKey> Trie<V, K> {
+    pub fn new() -> Self {
         Trie {
             children: Default::default(),
             node_count: Default::default(),
-            char_count: Default::default()
+            char_count: Default::default(),
+            key_count: Default::default(),
+            empty: None,
+            visit_tracker: None,
+            value_order: None,
         }
     }
-    pub fn insert(&mut self, text: &str,
-                  optional_associated_value: Option<V>) {
-        if text.is_empty() {
-            return
+
+    /// number of keys in the trie
+    pub fn len(&self) -> usize {
+        self.key_count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// removes all the keys
+    pub fn clear(&mut self) {
+        self.merge_visits();
+        self.children.clear();
+        self.empty = None;
+        self.node_count = 0;
+        self.char_count = 0;
+        self.key_count = 0;
+    }
+
+    /// collects size statistics about the trie, this walks over every node
+    pub fn stats(&self) -> TrieStats {
+        let mut stats = TrieStats {
+            node_count: self.node_count,
+            char_count: self.char_count,
+            key_count: self.len(),
+            ..Default::default()
+        };
+        let mut stack = self.children.values().map(|node| (node, 1)).collect::<Vec<_>>();
+        while let Some((node, depth)) = stack.pop() {
+            stats.max_depth = stats.max_depth.max(depth);
+            *stats.branching_factors.entry(node.children.len()).or_default() += 1;
+            stack.extend(node.children.values().map(|child| (child, depth + 1)));
         }
-        let c = text.chars().next().unwrap();
-        if let Some(child) = self.children.get_mut(&c) {
-            child.insert(text, optional_associated_value, &self.node_count, &self.char_count) ;
-        } else {
-            self.node_count.fetch_add(1, Relaxed);
-            self.char_count.fetch_add(text.len() as u32, Relaxed);
-            self.children.insert(c, Node {
-                text: text.to_string(),
-                terminal: true,
-                children: Default::default(),
-                value: optional_associated_value,
-                visit_count: Default::default(),
-                #[cfg(feature = "tracing")]
-                node_id: gen_id(),
-                weight: text.len()
-            });
+        if stats.node_count > 0 {
+            stats.average_edge_length = stats.char_count as f64 / stats.node_count as f64;
         }
+        stats
     }
-    /// removes the text from the trie and compresses nodes along the way
-    pub fn remove(&mut self, text: &str) {
-        let first = text.chars().next().unwrap();
-        if let Some(first) = self.children.get_mut(&first) {
-            first.remove(text, &self.node_count, &self.char_count);
+    /// inserts the text with the associated value
+    /// if the text was already present its value is replaced and the previous value is returned (like `HashMap::insert`)
+    pub fn insert(&mut self, text: &K,
+                  optional_associated_value: Option<V>) -> Option<V> {
+        let node = self.insert_node(text);
+        let previous = core::mem::replace(&mut node.value, optional_associated_value);
+        self.refresh_max_values();
+        previous
+    }
+
+    /// inserts the text but keeps the existing value if the text is already present
+    /// the given value is only used when the text is new or was inserted without a value
+    pub fn insert_if_absent(&mut self, text: &K,
+                            optional_associated_value: Option<V>) {
+        let node = self.insert_node(text);
+        if node.value.is_none() {
+            node.value = optional_associated_value;
         }
+        self.refresh_max_values();
     }
-    /// returns the suffix tree root for a given prefix
-    pub fn suffix_tree(&self, prefix: &str) -> Option<&Node<V>> {
-        if prefix.is_empty() {
-            return None
+
+    /// returns the node holding exactly this text as a key, splitting and creating nodes as needed
+    fn insert_node(&mut self, text: &K) -> &mut Node<V, K> {
+        // visits are recorded by node address, which may change from here on
+        self.merge_visits();
+        let node = match text.first_symbol() {
+            None => self.empty.get_or_insert_with(|| Node::new(text, false, None)),
+            Some(c) => match self.children.entry(c) {
+                CharMapEntry::Occupied(child) => {
+                    child.into_mut().insert_node(text, text.symbol_count(), &mut self.node_count, &mut self.char_count)
+                }
+                CharMapEntry::Vacant(slot) => {
+                    self.node_count += 1;
+                    self.char_count += text.key_len();
+                    slot.insert(Node::new(text, false, None))
+                }
+            }
+        };
+        if !node.terminal {
+            node.terminal = true;
+            self.key_count += 1;
+        }
+        // the nodes below the root level were marked on the way down
+        node.max_value_at = MaxValue::Stale;
+        node
+    }
+    /// returns the value associated with the exact key
+    /// returns None if the key was never inserted or if it was inserted without a value
+    pub fn get(&self, key: &K) -> Option<&V> {
+        self.find_node(key).and_then(|node| node.value.as_ref())
+    }
+
+    /// returns a mutable reference to the value associated with the exact key
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        self.find_node_mut(key).and_then(|node| node.value.as_mut())
+    }
+
+    /// true if the exact key was inserted (with or without a value)
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.find_node(key).is_some()
+    }
+
+    /// returns the terminal node holding exactly this key
+    fn find_node(&self, key: &K) -> Option<&Node<V, K>> {
+        let Some(first) = key.first_symbol() else {
+            return self.empty.as_ref()
+        };
+        let mut visits = self.visits();
+        let node = self.children.get(&first)
+            .and_then(|child| child.find_node(key, &mut visits))
+            .filter(|node| node.terminal);
+        self.record_visits(visits);
+        node
+    }
+
+    fn find_node_mut(&mut self, key: &K) -> Option<&mut Node<V, K>> {
+        let Some(first) = key.first_symbol() else {
+            let empty = self.empty.as_mut()?;
+            empty.max_value_at = MaxValue::Stale;
+            return Some(empty)
+        };
+        // logged like the read only queries, so the visits reach `max_visits` when they are merged
+        let mut visits = self.visits();
+        let node = self.children.get_mut(&first)
+            .and_then(|child| child.find_node_mut(key, &mut visits))
+            .filter(|node| node.terminal);
+        visits.record_into(&self.visit_tracker);
+        node
+    }
+
+    /// returns the longest key which is a prefix of the input along with its value
+    /// example: with the keys "/api" and "/api/v1/users", the input "/api/v1/users/42" matches "/api/v1/users"
+    pub fn longest_prefix_match<'k>(&self, input: &'k K) -> Option<(&'k K, Option<&V>)> {
+        let mut longest = None;
+        self.walk_prefixes(input, |len, node| longest = Some((input.split_key_at(len).0, node.value.as_ref())));
+        longest
+    }
+
+    /// returns every key which is a prefix of the input (shortest first) along with its value
+    pub fn prefixes_of<'k>(&self, input: &'k K) -> Vec<(&'k K, Option<&V>)> {
+        let mut prefixes = Vec::new();
+        self.walk_prefixes(input, |len, node| prefixes.push((input.split_key_at(len).0, node.value.as_ref())));
+        prefixes
+    }
+
+    /// walks down the input and calls `on_key` with the length (in units) of the key for every terminal node along the way
+    fn walk_prefixes<'a>(&'a self, input: &K, mut on_key: impl FnMut(usize, &'a Node<V, K>)) {
+        if let Some(empty) = &self.empty {
+            on_key(0, empty);
         }
-        let first = prefix.chars().next().unwrap();
-        if let Some(child) = self.children.get(&first) {
-            return child.suffix_root(prefix)
+        let mut visits = self.visits();
+        let mut consumed = 0;
+        let mut children = &self.children;
+        while let Some(c) = input.split_key_at(consumed).1.first_symbol() {
+            let Some(node) = children.get(&c) else {
+                break
+            };
+            visits.record(node);
+            if input.split_key_at(consumed).1.strip_key_prefix(node.text.borrow()).is_none() {
+                break
+            }
+            consumed += node.text.borrow().key_len();
+            if node.terminal {
+                on_key(consumed, node);
+            }
+            children = &node.children;
         }
-        None
+        self.record_visits(visits);
+    }
+
+    /// removes the text from the trie and compresses nodes along the way
+    /// returns the value which was associated with the text
+    pub fn remove(&mut self, text: &K) -> Option<V> {
+        let Some(first) = text.first_symbol() else {
+            let removed = self.empty.take()?;
+            self.key_count -= 1;
+            return removed.value
+        };
+        // visits are recorded by node address, which may change from here on
+        self.merge_visits();
+        let removed = self.children.get_mut(&first)?
+            .remove(text, &mut self.node_count, &mut self.char_count)?;
+        self.key_count -= 1;
+        compress_child(&mut self.children, first, &mut self.node_count, &mut self.char_count);
+        self.refresh_max_values();
+        removed
+    }
+}
+
+/// the queries which only make sense for string keys
+impl<V> Trie<V> {
+    /// returns the suffix tree root for a given prefix
+    /// returns None for an empty prefix, see `try_suffix_tree`
+    pub fn suffix_tree(&self, prefix: &str) -> Option<&Node<V>> {
+        self.try_suffix_tree(prefix).ok().flatten()
+    }
+
+    /// returns the suffix tree root for a given prefix
+    /// the root of the trie is not a node so an empty prefix is an error
+    pub fn try_suffix_tree(&self, prefix: &str) -> Result<Option<&Node<V>>, TrieError> {
+        let mut visits = self.visits();
+        let root = self.suffix_tree_visiting(prefix, &mut visits).map(|root| root.map(|(node, _)| node));
+        self.record_visits(visits);
+        root
     }
+
+    /// also returns the length (in bytes) of the text of the root covered by the prefix
+    fn suffix_tree_visiting(&self, prefix: &str, visits: &mut Visits) -> Result<Option<(&Node<V>, usize)>, TrieError> {
+        let first = prefix.chars().next().ok_or(TrieError::EmptyPrefix)?;
+        Ok(self.children.get(&first).and_then(|child| child.suffix_root_visiting(prefix, visits)))
+    }
+
     /// returns the suffix tree with the given matching options
+    /// returns None for an empty prefix, see `try_suffix_tree_with_matching_options`
     pub fn suffix_tree_with_matching_options(&self, prefix: &str, options: &MatchingOptions) -> Option<&Node<V>> {
-        let first = prefix.chars().next().unwrap();
-        if let Some(child) = self.children.get(&first) {
+        self.try_suffix_tree_with_matching_options(prefix, options).ok().flatten()
+    }
+
+    pub fn try_suffix_tree_with_matching_options(&self, prefix: &str, options: &MatchingOptions) -> Result<Option<&Node<V>>, TrieError> {
+        let mut visits = self.visits();
+        let root = self.suffix_tree_with_matching_options_visiting(prefix, options, &mut visits);
+        self.record_visits(visits);
+        root
+    }
+
+    fn suffix_tree_with_matching_options_visiting(&self, prefix: &str, options: &MatchingOptions, visits: &mut Visits) -> Result<Option<&Node<V>>, TrieError> {
+        let first = prefix.chars().next().ok_or(TrieError::EmptyPrefix)?;
+        Ok(self.children.get(&first).and_then(|child| {
             let tagged  = options.tag(prefix);
-            return child.suffix_tree_with_options(tagged.chars.as_slice(), options);
-        }
-        None
+            child.suffix_tree_with_options(tagged.chars.as_slice(), options, visits)
+        }))
     }
+
+    #[cfg(feature = "std")]
     pub fn get_string_suffixes(&self, prefix: &str) -> HashSet<String> {
         let mut coll = Vec::new();
         let mut emit = HashSet::new();
-        self.suffix_tree(prefix).map(|t| {
-            t.get_string_suffixes(true, prefix, &mut coll, &mut emit)
-        });
+        let mut visits = self.visits();
+        if let Ok(Some((t, covered))) = self.suffix_tree_visiting(prefix, &mut visits) {
+            t.string_suffixes(covered, &mut coll, &mut emit, &mut visits)
+        }
+        self.record_visits(visits);
         emit
     }
 
-    pub fn get_suffixes_values(&self, prefix: &str) -> Option<Vec<Entry<V>>> {
-        let mut coll = Vec::new();
-        self.suffix_tree(prefix).map(|t| {
-            t.get_suffixes(true, prefix,  &mut coll)
-        })
+    pub fn get_suffixes_values(&self, prefix: &str) -> Option<Vec<Entry<'_, V>>> {
+        self.try_get_suffixes_values(prefix).ok().flatten()
     }
 
-    pub fn get_suffixes_with_matching_options(&self, prefix: &str, options: &MatchingOptions) -> Option<Vec<Entry<V>>> {
-        let mut coll = Vec::new();
-        self.suffix_tree_with_matching_options(prefix, options).map(|t| {
-            t.get_suffixes(true, prefix,  &mut coll)
-        })
+    /// like `get_suffixes_values` but an empty prefix is an error instead of None
+    pub fn try_get_suffixes_values(&self, prefix: &str) -> Result<Option<Vec<Entry<'_, V>>>, TrieError> {
+        let mut suffix = String::new();
+        let mut visits = self.visits();
+        let suffixes = self.suffix_tree_visiting(prefix, &mut visits).map(|root| root.map(|(t, covered)| {
+            t.get_suffixes(covered, &mut suffix, &mut visits)
+        }));
+        self.record_visits(visits);
+        suffixes
+    }
+
+    /// returns the suffixes of the keys matching the prefix under the matching options
+    /// this is the fast "best branch" strategy: where the options allow several branches only the heaviest one is followed,
+    /// see `get_keys_with_matching_options` for all of them
+    pub fn get_suffixes_with_matching_options(&self, prefix: &str, options: &MatchingOptions) -> Option<Vec<Entry<'_, V>>> {
+        self.try_get_suffixes_with_matching_options(prefix, options).ok().flatten()
     }
+
+    /// like `get_suffixes_with_matching_options` but an empty prefix is an error instead of None
+    pub fn try_get_suffixes_with_matching_options(&self, prefix: &str, options: &MatchingOptions) -> Result<Option<Vec<Entry<'_, V>>>, TrieError> {
+        let mut suffix = String::new();
+        let mut visits = self.visits();
+        let suffixes = self.suffix_tree_with_matching_options_visiting(prefix, options, &mut visits).map(|root| root.map(|t| {
+            // the fuzzy match does not tell where it ended in the root, this guesses it from the overlap
+            let covered = find_common_overlap_of_prefix_with_node(prefix, t.text.as_str()).unwrap_or(0);
+            t.get_suffixes(covered, &mut suffix, &mut visits)
+        }));
+        self.record_visits(visits);
+        suffixes
+    }
+
+    /// returns every key (in full) matching the prefix under the matching options, in lexicographic order
+    /// unlike `get_suffixes_with_matching_options` every branch compatible with the options is explored,
+    /// e.g. ignoring white space "roma" finds both "romanus" and "rom anus"
+    pub fn get_keys_with_matching_options(&self, prefix: &str, options: &MatchingOptions) -> Vec<Entry<'_, V>> {
+        let tagged = options.tag(prefix);
+        let mut collector = Vec::new();
+        let mut key = String::new();
+        let mut visits = self.visits();
+        let roots = self.empty.iter().chain(iter::stack_order(&self.children).into_iter().rev());
+        for root in roots {
+            root.collect_matching(tagged.chars.as_slice(), options, &mut key, &mut collector, &mut visits);
+        }
+        self.record_visits(visits);
+        collector
+    }
+}
+
+/// size statistics of a trie, see `Trie::stats`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TrieStats {
+    pub node_count: usize,
+    /// total length (in units, bytes for strings) of the text held by all the nodes
+    pub char_count: usize,
+    /// number of keys (terminal nodes)
+    pub key_count: usize,
+    /// number of nodes on the longest path from the root
+    pub max_depth: usize,
+    /// average length (in units, bytes for strings) of the text held by a node
+    pub average_edge_length: f64,
+    /// number of children => number of nodes with that many children
+    pub branching_factors: alloc::collections::BTreeMap<usize, usize>,
 }
 
-#[derive(Serialize, Deserialize)]
-pub struct Node<V> {
-    text: String,
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(
+    from = "SerializedNode<V, K>",
+    bound(
+        serialize = "V: Serialize, K::Owned: Serialize, K::Symbol: Serialize",
+        deserialize = "V: Deserialize<'de>, K::Owned: Deserialize<'de>, K::Symbol: Deserialize<'de>",
+    ),
+))]
+pub struct Node<V, K: ?Sized + TrieKey = str> {
+    text: K::Owned,
     terminal: bool,
-    children: HashMap<char, Node<V>>,
+    children: CharMap<K::Symbol, Node<V, K>>,
     value: Option<V>,
-    // for pruning purposes
-    #[serde(default)]
-    visit_count: std::sync::atomic::AtomicU64, // TODO: this didn't need to be atomic
+    // for pruning purposes, only counted while visit tracking is on (see `Trie::track_visits`)
+    #[cfg_attr(feature = "serde", serde(default))]
+    visit_count: u64,
     #[cfg(feature = "tracing")]
-    #[serde(default = "gen_id")]
+    #[cfg_attr(feature = "serde", serde(default = "gen_id"))]
     node_id: Option<u8>, // TODO: u8 might not be enough ? consider bigger
-    #[serde(default)]
+    #[cfg_attr(feature = "serde", serde(default))]
     weight: usize,
+    /// length (in symbols) of the longest key in the subtree counted from the start of this node, bounds `rank::ByKeyLength`
+    #[cfg_attr(feature = "serde", serde(skip))]
+    max_len: usize,
+    /// the largest visit_count in the subtree of this node, bounds `rank::ByVisitCount`
+    #[cfg_attr(feature = "serde", serde(skip))]
+    max_visits: u64,
+    /// where the largest value in the subtree of this node is, bounds `rank::ByValue`
+    #[cfg_attr(feature = "serde", serde(skip))]
+    max_value_at: MaxValue<K::Symbol>,
 }
 
-impl <V:Clone> Clone for Node<V> {
-    fn clone(&self) -> Self {
-        Node {
-            text: self.text.clone(),
-            terminal: self.terminal,
-            children: self.children.clone(),
-            value: self.value.clone(),
-            visit_count: std::sync::atomic::AtomicU64::new(self.visit_count.load(Relaxed)),
+/// the serialized fields of a node, the weight and the bounds are recomputed from the (already deserialized) children
+#[cfg(feature = "serde")]
+#[derive(Deserialize)]
+#[serde(bound = "V: Deserialize<'de>, K::Owned: Deserialize<'de>, K::Symbol: Deserialize<'de>")]
+struct SerializedNode<V, K: ?Sized + TrieKey> {
+    text: K::Owned,
+    terminal: bool,
+    children: CharMap<K::Symbol, Node<V, K>>,
+    value: Option<V>,
+    #[serde(default)]
+    visit_count: u64,
+    #[cfg(feature = "tracing")]
+    #[serde(default = "gen_id")]
+    node_id: Option<u8>,
+}
+
+#[cfg(feature = "serde")]
+impl<V, K: ?Sized + TrieKey> From<SerializedNode<V, K>> for Node<V, K> {
+    fn from(node: SerializedNode<V, K>) -> Self {
+        let mut node = Node {
+            text: node.text,
+            terminal: node.terminal,
+            children: node.children,
+            value: node.value,
+            visit_count: node.visit_count,
             #[cfg(feature = "tracing")]
-            node_id: self.node_id.clone(),
-            weight: self.weight
-        }
+            node_id: node.node_id,
+            weight: 0,
+            max_len: 0,
+            max_visits: 0,
+            max_value_at: MaxValue::Stale,
+        };
+        node.weight = node.text.borrow().key_len() + node.char_weight_of_children();
+        node.refresh_bounds();
+        node
     }
 }
 
@@ -145,8 +529,26 @@ fn gen_id() -> Option<u8> {
     Some(rng.gen())
 }
 
-impl<V> Debug for Node<V> where V: Debug {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+impl<V: Clone, K: ?Sized + TrieKey> Clone for Node<V, K> {
+    fn clone(&self) -> Self {
+        Node {
+            text: self.text.clone(),
+            terminal: self.terminal,
+            children: self.children.clone(),
+            value: self.value.clone(),
+            visit_count: self.visit_count,
+            #[cfg(feature = "tracing")]
+            node_id: self.node_id,
+            weight: self.weight,
+            max_len: self.max_len,
+            max_visits: self.max_visits,
+            max_value_at: self.max_value_at.clone(),
+        }
+    }
+}
+
+impl<V: Debug, K: ?Sized + TrieKey> Debug for Node<V, K> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
         f.debug_struct("Node")
             .field("text", &self.text)
             .field("terminal", &self.terminal)
@@ -156,252 +558,213 @@ impl<V> Debug for Node<V> where V: Debug {
     }
 }
 
-impl<V> Node<V> {
-    pub fn new(string: &str, is_terminal: bool, value: Option<V>) -> Self {
+impl<V, K: ?Sized + TrieKey> Node<V, K> {
+    pub fn new(text: &K, is_terminal: bool, value: Option<V>) -> Self {
         Node {
-            text: string.to_string(),
+            text: text.to_owned_key(),
             terminal: is_terminal,
             children: Default::default(),
             value,
             visit_count: Default::default(),
             #[cfg(feature = "tracing")]
             node_id: gen_id(),
-            weight: string.len()
+            weight: text.key_len(),
+            max_len: text.symbol_count(),
+            max_visits: Default::default(),
+            max_value_at: MaxValue::Stale,
         }
     }
 
-    pub fn remove(&mut self, text: &str,
-                  node_count: &std::sync::atomic::AtomicU32,
-                  char_count: &std::sync::atomic::AtomicU32) {
-        let mut position = 0;
-        let mut my_iter = self.text.chars();
-        let mut other_iter = text.chars();
-        loop {
-            let my_char = my_iter.next();
-            let other_char = other_iter.next();
-            let remaining = grapheme_slicer_until_end(text, position);
-            match (my_char, other_char) {
-                // this = abcd
-                // other = abce
-                (Some(x), Some(y)) if x != y => {
-                    // stop
-                    return
-                },
-                (Some(x), Some(y)) if x == y => {
-                    // more to come
-                },
-                (Some(_), None) => {
-                    return
-                },
-                (None, Some(y)) => {
-                    if let Some(child) = self.children.get_mut(&y) {
-                        child.remove(remaining.as_str(), node_count, char_count);
-                        if child.children.len() == 0 {
-                            node_count.fetch_sub(1, Relaxed);
-                            char_count.fetch_sub(child.text.len() as u32, Relaxed);
-                            self.weight -= child.weight;
-                            if !child.terminal {
-                                self.children.remove(&y); // removing dangling child
-                            }
-                        }
-                        break;
-                    }
-                    // this prefix never existed in this tree
-                    return
-                },
-                (None, None) => {
-                    // this case we need to remove this node possibly
-                    if self.children.len() == 1 {
-                        break
-                    }
-                    // make zombie to be removed
-                    self.terminal = false;
-                    return
-
-                },
-                _ => {}
+    /// removes the text from the subtree of this node
+    /// returns None if the text is not a key under this node, otherwise the value that was associated with it
+    /// the caller is responsible for pruning or merging this node afterwards (see `compress_child`)
+    fn remove(&mut self, text: &K,
+              node_count: &mut usize,
+              char_count: &mut usize) -> Option<Option<V>> {
+        self.max_value_at = MaxValue::Stale;
+        let remaining = text.strip_key_prefix(self.text.borrow())?;
+        match remaining.first_symbol() {
+            None => {
+                if !self.terminal {
+                    // this prefix was never inserted as a key
+                    return None
+                }
+                // make zombie to be removed or merged by the parent
+                self.terminal = false;
+                Some(self.value.take())
+            }
+            Some(c) => {
+                let removed = self.children.get_mut(&c)?.remove(remaining, node_count, char_count)?;
+                compress_child(&mut self.children, c, node_count, char_count);
+                self.weight = self.text.borrow().key_len() + self.char_weight_of_children();
+                self.refresh_bounds();
+                Some(removed)
             }
-            position += 1;
-        }
-        if self.children.len() == 1 {
-            // merge this with the child
-            let (_, child) = self.children.drain().next().unwrap();
-            node_count.fetch_sub(1, Relaxed);
-            // same text which is merged back
-            self.text = format!("{}{}", self.text, child.text);
-            self.value = child.value;
-            self.terminal = child.terminal;
-            self.children = child.children;
-            return
         }
     }
 
+    /// merges this (non terminal) node with its single child
+    fn merge_with_child(&mut self, node_count: &mut usize) {
+        let child = core::mem::take(&mut self.children).into_values().next().unwrap();
+        *node_count -= 1;
+        // same text which is merged back
+        K::push_key(&mut self.text, child.text.borrow());
+        self.value = child.value;
+        self.terminal = child.terminal;
+        self.children = child.children;
+        // the subtree of the child is the one of the merged node
+        self.max_value_at = child.max_value_at;
+        self.refresh_bounds();
+    }
+
+    /// recomputes `max_len` and `max_visits` from the children
+    fn refresh_bounds(&mut self) {
+        let children = self.children.values();
+        self.max_len = self.text.borrow().symbol_count() + children.clone().map(|child| child.max_len).max().unwrap_or(0);
+        self.max_visits = children.map(|child| child.max_visits).fold(self.visit_count, u64::max);
+    }
+
+    /// recomputes `max_visits` in the whole subtree, returns the one of this node
+    #[cfg(feature = "std")]
+    fn refresh_max_visits(&mut self) -> u64 {
+        self.max_visits = self.children.values_mut()
+            .map(|child| child.refresh_max_visits())
+            .fold(self.visit_count, u64::max);
+        self.max_visits
+    }
+
     pub fn char_weight_of_children(&self) -> usize {
         self.children
-            .iter()
-            .fold(0, |x, (_,y) | x + y.weight)
+            .values()
+            .map(|child| child.weight)
+            .sum()
     }
 
-    pub fn insert(&mut self, text: &str,
-                  value: Option<V>,
-                  node_count: &std::sync::atomic::AtomicU32,
-                  char_count: &std::sync::atomic::AtomicU32) {
-        let mut position = 0;
-        let mut child_iter = text.chars();
-        let mut this_iter = self.text.chars();
-        loop {
-            let text_next_char = child_iter.next();
-            let this_next_char = this_iter.next();
-
-            match (text_next_char, this_next_char) {
-                (Some(x), Some(y)) if x == y => {
-                    // continue
-                },
-                (Some(input_next), Some(this_next)) if input_next != this_next => {
-                    // split
-                    let current_child_weight = self.char_weight_of_children();
-                    let existing_remainder = grapheme_slicer_until_end(self.text.as_str(), position);
-                    // let existing_remainder = self.text[position..].to_string();
-
-                    let mut new_node = Node {
-                        text: existing_remainder.clone(),
-                        terminal: self.terminal, // if I was terminal, then suffice to say my splitted up self is also terminal
-                        children: Default::default(),
-                        value: None,
-                        visit_count: std::sync::atomic::AtomicU64::new(self.visit_count()),
-                        #[cfg(feature = "std")]
-                        node_id: gen_id(),
-                        weight: current_child_weight + existing_remainder.len()
-                    };
-                    // exhange my children for the new node (I am empty and will add a new node back)
-                    std::mem::swap(&mut new_node.children, &mut self.children);
-                    std::mem::swap(&mut new_node.value, &mut self.value);
-                    let first_char_of_existing_remainder = existing_remainder.chars().next().unwrap();
-                    // new node was created but same num chars which was split between 2 nodes
-                    node_count.fetch_add(1, Relaxed);
-                    self.children.insert(first_char_of_existing_remainder, new_node);
-
-
-                    let common = grapheme_slicer_until_point(text, position);
-
-                    let remainder = grapheme_slicer_until_end(text, position);
-                    // let common = &text[0..position];
-                    // let remainder = &text[position..];
-
-                    let input_new_node = Node {
-                        text: remainder.to_string(),
-                        terminal: true,
-                        children: Default::default(),
-                        value,
-                        visit_count: std::sync::atomic::AtomicU64::new(self.visit_count()),
-                        #[cfg(feature = "tracing")]
-                        node_id: gen_id(),
-                        weight: remainder.len()
-                    };
-
-                    let c = remainder.chars().next().unwrap();
-                    // new node added
-                    node_count.fetch_add(1, Relaxed);
-                    // remainder chars added to tree
-                    char_count.fetch_add(remainder.len() as u32, Relaxed);
-                    self.children.insert(c, input_new_node);
-                    // let delta_weight = self.text.len() - common.len();
-                    self.text = common.to_string();
-                    self.terminal = false;
-                    self.weight = self.text.len() + self.char_weight_of_children();
-                    // self.weight += (delta_weight + remainder.len());
-                    return;
+    /// moves everything after `at` (a unit offset on a symbol boundary of the text) into a new child node
+    /// this node keeps the text up to `at` and becomes a non terminal node without a value
+    fn split_at(&mut self, at: usize, node_count: &mut usize) {
+        let existing_remainder = K::split_off_key(&mut self.text, at);
+        let first_char_of_existing_remainder = existing_remainder.borrow().first_symbol().unwrap();
+        let new_node = Node {
+            weight: self.weight - self.text.borrow().key_len(),
+            text: existing_remainder,
+            // if I was terminal, then suffice to say my splitted up self is also terminal
+            terminal: core::mem::take(&mut self.terminal),
+            // exchange my children for the new node (I am empty and will add a new node back)
+            children: core::mem::take(&mut self.children),
+            value: self.value.take(),
+            visit_count: self.visit_count,
+            #[cfg(feature = "tracing")]
+            node_id: gen_id(),
+            max_len: self.max_len - self.text.borrow().symbol_count(),
+            max_visits: self.max_visits,
+            // the subtree moves as a whole, this node holds it and the new key
+            max_value_at: core::mem::take(&mut self.max_value_at),
+        };
+        // new node was created but same num chars which was split between 2 nodes
+        *node_count += 1;
+        self.children.insert(first_char_of_existing_remainder, new_node);
+    }
 
+    /// returns the node holding exactly the text, splitting this node or adding children as needed
+    /// the text is expected to start with the first symbol of this node, `symbols` is its symbol count
+    /// (passed down so it is not counted again on every level)
+    fn insert_node(&mut self, text: &K, symbols: usize,
+                   node_count: &mut usize,
+                   char_count: &mut usize) -> &mut Node<V, K> {
+        let common = self.text.borrow().common_prefix_len(text);
+        if common < self.text.borrow().key_len() {
+            self.split_at(common, node_count);
+        }
+        self.max_value_at = MaxValue::Stale;
+        self.max_len = self.max_len.max(symbols);
+        let remainder = text.split_key_at(common).1;
+        let Some(c) = remainder.first_symbol() else {
+            return self
+        };
+        let chars_before = *char_count;
+        let visit_count = self.visit_count;
+        let node = match self.children.entry(c) {
+            CharMapEntry::Occupied(child) => {
+                let symbols = symbols - self.text.borrow().symbol_count();
+                child.into_mut().insert_node(remainder, symbols, node_count, char_count)
+            }
+            CharMapEntry::Vacant(slot) => {
+                *node_count += 1;
+                *char_count += remainder.key_len();
+                let mut new_node = Node::new(remainder, false, None);
+                new_node.visit_count = visit_count;
+                new_node.max_visits = visit_count;
+                slot.insert(new_node)
+            }
+        };
+        // every char added below this node adds to its weight
+        self.weight += *char_count - chars_before;
+        node
+    }
 
+    /// returns the node whose accumulated text is exactly the key (terminal or not)
+    /// the key is expected to start with this node's text
+    fn find_node(&self, key: &K, visits: &mut Visits) -> Option<&Node<V, K>> {
+        visits.record(self);
+        let rest = key.strip_key_prefix(self.text.borrow())?;
+        match rest.first_symbol() {
+            None => Some(self),
+            Some(c) => self.children.get(&c)?.find_node(rest, visits)
+        }
+    }
 
-                }
-                (None, Some(c)) => {
-                    // let prefix = self.text[0..position].to_string();
-                    // let remainder = self.text[position..].to_string();
-
-                    let prefix = grapheme_slicer_until_point(self.text.as_str(), position);
-
-                    let remainder = grapheme_slicer_until_end(self.text.as_str(), position);
-                    self.text = prefix.to_string();
-                    self.terminal = true;
-                    // self.nested_chars += remainder.len();
-                    let current_child_weight = self.char_weight_of_children();
-                    if let Some(child) = self.children.get_mut(&c) {
-                        let taken = std::mem::take(&mut self.value);
-                        self.value = value;
-
-                        child.insert(remainder.as_str(), taken, node_count, char_count);
-                        let new_char_weight = self.char_weight_of_children();
-                        let delta = new_char_weight - current_child_weight;
-                        self.weight += delta;
-                        return
-                    }
-                    let new_node = Node {
-                        text: remainder.to_string(),
-                        terminal: true,
-                        children: Default::default(),
-                        value,
-                        visit_count: std::sync::atomic::AtomicU64::new(self.visit_count()),
-                        #[cfg(feature = "tracing")]
-                        node_id: gen_id(),
-                        weight: remainder.len()
-                    };
-                    node_count.fetch_add(1, Relaxed);
-                    char_count.fetch_add(remainder.len() as u32, Relaxed);
-                    self.children.insert(c, new_node);
-                    self.terminal = true;
-                    return;
-                },
-                (Some(text_next), None) => {
-                    let remainder = grapheme_slicer_until_end(text, position);
-                    // let remainder = &text[position..];
-
-                    // self.nested_chars += remainder.len();
-                    let current_child_weight = self.char_weight_of_children();
-                    if let Some(next) = self.children.get_mut(&text_next) {
-
-                        next.insert(remainder.as_str(), value, node_count, char_count);
-                        let new_weight_of_children = self.char_weight_of_children();
-                        let delta = new_weight_of_children - current_child_weight;
-                        self.weight += delta;
-                        return
-                    }
-                    // make new child
-                    let new_node = Node {
-                        text: remainder.to_string(),
-                        terminal: true,
-                        children: Default::default(),
-                        value,
-                        visit_count: std::sync::atomic::AtomicU64::new(self.visit_count()),
-                        #[cfg(feature = "tracing")]
-                        node_id: gen_id(),
-                        weight: remainder.len()
-                    };
-                    self.weight += remainder.len();
-                    node_count.fetch_add(1, Relaxed);
-                    char_count.fetch_add(remainder.len() as u32, Relaxed);
-                    self.children.insert(text_next, new_node);
-                    return;
+    /// the value of the returned node may be changed, so the nodes on the way are marked as not bounding their values
+    fn find_node_mut(&mut self, key: &K, visits: &mut Visits) -> Option<&mut Node<V, K>> {
+        visits.record(self);
+        self.max_value_at = MaxValue::Stale;
+        let rest = key.strip_key_prefix(self.text.borrow())?;
+        match rest.first_symbol() {
+            None => Some(self),
+            Some(c) => self.children.get_mut(&c)?.find_node_mut(rest, visits)
+        }
+    }
 
-                }
-                (None, None) => {
-                    self.terminal = true;
-                    if self.value.is_none() {
-                        self.value = value;
-                    }
-                    return;
-                }
-                _ => {panic!("Should never be here {} {}", self.text, text )} // compiler yells that it wants this case but I don't see how it could occur
-            }
-            position += 1;
+    /// number of times this node was visited by queries while visit tracking was on
+    /// visits which were not merged yet are not included (see `Trie::merge_visits`)
+    pub fn visit_count(&self) -> u64 {
+        self.visit_count
+    }
 
+    /// the largest `visit_count` in the subtree of this node (including itself)
+    pub fn max_visit_count(&self) -> u64 {
+        self.max_visits
+    }
 
+    /// the length (in symbols, chars for strings) of the longest key in the subtree of this node, counted from the start of this node
+    pub fn max_key_len(&self) -> usize {
+        self.max_len
+    }
 
+    /// the largest value in the subtree of this node (including its own), None if there is none or it is not known
+    /// (see `Trie::track_max_values`)
+    pub fn max_value(&self) -> Option<&V> {
+        let mut node = self;
+        loop {
+            match &node.max_value_at {
+                MaxValue::Here => return node.value.as_ref(),
+                MaxValue::Below(symbol) => node = node.children.get(symbol)?,
+                MaxValue::Stale | MaxValue::Nothing => return None,
+            }
         }
     }
 
-    fn match_on_treated_suffix_trees(&self, prefix: &[(Tagged, Offset)], options: &MatchingOptions) -> Vec<&Node<V>> {
-        if prefix.len() == 0 {
+    /// the value of this node, None if it is not a key or the key was inserted without a value
+    pub fn value(&self) -> Option<&V> {
+        self.value.as_ref()
+    }
+
+}
+
+/// the fuzzy matching, which only makes sense for string keys
+impl<V> Node<V> {
+    fn match_on_treated_suffix_trees(&self, prefix: &[(Tagged, Offset)], options: &MatchingOptions, visits: &mut Visits) -> Vec<&Node<V>> {
+        if prefix.is_empty() {
             return vec![];
         }
         // i don't think this is necessarily correct - example if you have multiple whitespaces
@@ -410,25 +773,23 @@ impl<V> Node<V> {
         let mut v = self.children.iter().filter(|(key, _node)| {
             key == tagged_char ||
             options.treatments.contains_key(key)
-        } ).map(|(_, n)| {
-            n.suffix_tree_with_options(prefix, options)
-        }).flatten().collect::<Vec<_>>();
-        v.sort_by(|x,y| y.weight.cmp(&x.weight));
+        } ).filter_map(|(_, n)| {
+            n.suffix_tree_with_options(prefix, options, visits)
+        }).collect::<Vec<_>>();
+        v.sort_by_key(|x| core::cmp::Reverse(x.weight));
         v
     }
 
-    fn suffix_tree_with_options(&self, prefix: &[(Tagged, Offset)], options: &MatchingOptions) -> Option<&Node<V>> {
+    fn suffix_tree_with_options(&self, prefix: &[(Tagged, Offset)], options: &MatchingOptions, visits: &mut Visits) -> Option<&Node<V>> {
         // update visit count
-
-
-        self.visit_count.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        visits.record(self);
         let self_tagged = options.tag(self.text.as_str());
         #[cfg(feature = "tracing")]
         if true {
             trace!("this: {:?} {:?}", self.text, self.node_id);
             trace!("self_tagged: {:?}", self_tagged);
             trace!("prefix: {:?}", prefix);
-            trace!("children {:?}", self.children.keys());
+            trace!("children {:?}", self.children.keys().collect::<Vec<_>>());
         }
 
         let this_iter = self_tagged.chars.iter();
@@ -444,13 +805,11 @@ impl<V> Node<V> {
             x if x < prefix.len() && x < self_tagged.chars.len() => {
                 // we have a mismatch at some position and need to attempt to branch
                 let (_,offset) = self_tagged.chars.get(x).unwrap();
-                let continuation = grapheme_slicer_until_end(self.text.as_str(), *offset);
-                // let continuation = &self.text.as_str()[*offset..];
-                let child_ = continuation.chars().next().unwrap();
+                let child_ = self.text[*offset..].chars().next().unwrap();
                 let new_prefix = &prefix[x..];
-                let best_attempt = self.match_on_treated_suffix_trees(new_prefix, options).into_iter().next();
+                let best_attempt = self.match_on_treated_suffix_trees(new_prefix, options, visits).into_iter().next();
                 if let Some(child) = self.children.get(&child_) {
-                    let c = child.suffix_tree_with_options(new_prefix, options);
+                    let c = child.suffix_tree_with_options(new_prefix, options, visits);
                     match (best_attempt, c) {
                         (Some(best_attempt), Some(c)) if best_attempt.weight > c.weight => return Some(best_attempt),
                         (Some(best_attempt), _) => return Some(best_attempt),
@@ -462,11 +821,11 @@ impl<V> Node<V> {
                     // }
                     // return child.suffix_tree_with_options(new_prefix, options)
                 }
-                return best_attempt;
+                best_attempt
                 // return None
             }
             x if x == prefix.len() && x <= self_tagged.chars.len() => {
-                return Some(self) // this is the terminating node
+                Some(self) // this is the terminating node
             }
             x if x == self_tagged.chars.len() && x < prefix.len() => {
                 let (last,_o) = prefix.get(x).unwrap();
@@ -476,19 +835,21 @@ impl<V> Node<V> {
                         // perhaps the better solution is to return both options
                         // TODO: define the proper strategy here
                         let new_prefix = &prefix[x..];
-                        let mut treated = self.match_on_treated_suffix_trees(new_prefix, options);
+                        let mut treated = self.match_on_treated_suffix_trees(new_prefix, options, visits);
                         treated.sort_by(|x,y| {
                             y.weight.partial_cmp(&x.weight).unwrap()
                         });
                         let best = treated.into_iter().next();
 
                         if let Some(child) = self.children.get(c) {
-                            let c = child.suffix_tree_with_options(new_prefix, options);
+                            let c = child.suffix_tree_with_options(new_prefix, options, visits);
                             match (best,c) {
                                 (Some(best), Some(c)) if best.weight > c.weight => {
+                                    #[cfg(feature = "tracing")]
                                     trace!("choose fuzzy over exact match");
                                     return Some(best)}
                                 (Some(best), _) => {
+                                    #[cfg(feature = "tracing")]
                                     trace!("choose fuzzy (no exact match)");
                                     return Some(best)
                                 },
@@ -497,7 +858,7 @@ impl<V> Node<V> {
                             }
 
                         }
-                        return best
+                        best
                     }
 
                 }
@@ -505,8 +866,51 @@ impl<V> Node<V> {
             }
             _ => {
                 // should not get here
-                return None
+                None
+            }
+        }
+    }
+
+    /// collects the keys in this subtree whose tagged text continues the (rest of the) tagged prefix
+    /// once the prefix is used up the whole subtree matches, so overlapping candidates are only collected once
+    /// `key` is the key leading up to this node
+    fn collect_matching<'a>(&'a self, prefix: &[(Tagged, Offset)], options: &MatchingOptions,
+                            key: &mut String, collector: &mut Vec<Entry<'a, V>>, visits: &mut Visits) {
+        visits.record(self);
+        let mut rest = prefix;
+        for (tagged, _) in options.tag(self.text.as_str()).chars.iter() {
+            let Some(((expected, _), remaining)) = rest.split_first() else {
+                break
+            };
+            if expected != tagged {
+                return
             }
+            rest = remaining;
+        }
+        let len = key.len();
+        key.push_str(self.text.as_str());
+        if rest.is_empty() {
+            self.collect_all(key, collector, visits);
+        } else {
+            for child in iter::stack_order(&self.children).into_iter().rev() {
+                child.collect_matching(rest, options, key, collector, visits);
+            }
+        }
+        key.truncate(len);
+    }
+
+    /// collects every key of this subtree in lexicographic order, `key` ends with the text of this node
+    /// the visit of this node is left to the caller, the nodes below are recorded like the other queries do
+    fn collect_all<'a>(&'a self, key: &mut String, collector: &mut Vec<Entry<'a, V>>, visits: &mut Visits) {
+        if self.terminal {
+            collector.push(Entry { key: key.clone(), val: &self.value });
+        }
+        let len = key.len();
+        for child in iter::stack_order(&self.children).into_iter().rev() {
+            visits.record(child);
+            key.push_str(child.text.as_str());
+            child.collect_all(key, collector, visits);
+            key.truncate(len);
         }
     }
 
@@ -516,16 +920,23 @@ impl<V> Node<V> {
     /// include_partial: false => None
     /// include_partial: true => Some(self) // overlaps on abc
     pub fn suffix_root(&self, prefix: &str) -> Option<&Node<V>> {
+        self.suffix_root_visiting(prefix, &mut Visits::off()).map(|(node, _)| node)
+    }
+
+    /// returns the node the prefix ends in along with the length (in bytes) of its text covered by the prefix
+    fn suffix_root_visiting(&self, prefix: &str, visits: &mut Visits) -> Option<(&Node<V>, usize)> {
         // update visit count
-        self.visit_count.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
-        let mut position = 0;
-        let mut this_iter = self.text.chars();
-        let mut other_iter = prefix.chars();
+        visits.record(self);
+        let mut this_iter = self.text.char_indices();
+        let mut other_iter = prefix.char_indices();
         loop {
-            let this_next_char = this_iter.next();
-            let other_next_char = other_iter.next();
-            match (this_next_char, other_next_char) {
-                (Some(x), Some(y)) => {
+            let this_next = this_iter.next();
+            let (position, other_next_char) = match other_iter.next() {
+                Some((position, c)) => (position, Some(c)),
+                None => (prefix.len(), None),
+            };
+            match (this_next, other_next_char) {
+                (Some((_, x)), Some(y)) => {
                     if x != y {
                         // return nothing
                         if !x.is_whitespace() && !y.is_whitespace() {
@@ -536,93 +947,73 @@ impl<V> Node<V> {
                 },
                 (None, Some(x)) => {
                     if let Some(next_child) = self.children.get(&x) {
-                        return next_child.suffix_root(grapheme_slicer_until_end(prefix, position).as_str())
+                        return next_child.suffix_root_visiting(&prefix[position..], visits)
                     }
                     return None
                 }
-                (Some(x), None) => {
-                    if let Some(child) = self.children.get(&x) {
-                        return child.suffix_root(prefix)
-                    }
-                    return Some(self)
+                // the prefix ends within the text of this node
+                (Some((offset, _)), None) => {
+                    return Some((self, offset))
                 }
                 (None, None) => {
-                    return Some(self);
+                    return Some((self, self.text.len()));
                 }
             }
-            position += 1;
         }
     }
 
     /// returns all the suffixes below this node
-    /// notice that we pass the prefix as an input parameter because there may be some overlap between the node contents and the prefix which needs to be stripped away
-    /// for example a tree like "ab" -> "cde"
-    /// for a given input "abc" we would be lead to this node
-    /// so we want to return results like "abcde" and not "abccde" (notice the "c" appears twice if blindly appending)
-    /// notice that there is an edge case here which is not yet handled where the match might have been fuzzy with options and that overlap is not handled correctly
-    /// for instance if this node text ends with white space
-    fn get_suffixes<'a>(&'a self, is_root: bool, prefix: &str, collector: &mut Vec<String>) -> Vec<Entry<'a, V>> {
+    /// the first `covered` bytes of the text of this node are already part of the prefix and are stripped away,
+    /// for example with a tree like "ab" -> "cde" and the prefix "abc" the suffixes start with "de", not "cde"
+    /// `suffix` holds the suffix collected on the way down and is restored before returning
+    fn get_suffixes<'a>(&'a self, covered: usize, suffix: &mut String, visits: &mut Visits) -> Vec<Entry<'a, V>> {
         // update visit count
-        self.visit_count.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        visits.record(self);
+        let len = suffix.len();
         //Entry
-        if !is_root {
-            collector.push(self.text.clone());
-        } else {
-            if let Some(pos) = find_common_overlap_of_prefix_with_node(prefix, self.text.as_str()) {
-                if pos < self.text.len() {
-                    collector.push(grapheme_slicer_until_end(self.text.as_str(), pos));
-                }
-            } else {
-                collector.push(self.text.clone());
-            }
-        }
+        suffix.push_str(&self.text[covered..]);
 
         let mut v: Vec<Entry<'a ,V>> = Vec::<Entry<V>>::new();
         if self.terminal {
-            let jo = collector.join("");
             let entry: Entry<'a, V> = Entry {
-                key: jo,
+                key: suffix.clone(),
                 val: &self.value
             };
             v.push(entry);
         }
-        let mut children = self.children.values();
-        while let Some(child) = children.next() {
-            let mut add = child.get_suffixes(false, prefix, collector);
+        for child in self.children.values() {
+            let mut add = child.get_suffixes(0, suffix, visits);
             v.append(&mut add);
         }
-        collector.pop();
+        suffix.truncate(len);
         v
     }
 
+    /// collects the suffixes below this node, for the root (`is_root`) the overlap of its text with the prefix is stripped away
+    #[cfg(feature = "std")]
     pub fn get_string_suffixes(&self, is_root: bool, prefix: &str, collector: &mut Vec<String>, emit: &mut HashSet<String>) {
-        // update visit count
-        self.visit_count.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
-        if !is_root {
-            collector.push(self.text.clone());
-        } else {
-            if let Some(pos) = find_common_overlap_of_prefix_with_node(prefix, self.text.as_str()) {
-                if pos < self.text.len() {
-                    collector.push(grapheme_slicer_until_end(self.text.as_str(), pos));
-                }
-            }
+        let covered = match is_root {
+            true => find_common_overlap_of_prefix_with_node(prefix, self.text.as_str()).unwrap_or(self.text.len()),
+            false => 0,
+        };
+        self.string_suffixes(covered, collector, emit, &mut Visits::off())
+    }
 
-        }
+    #[cfg(feature = "std")]
+    fn string_suffixes(&self, covered: usize, collector: &mut Vec<String>, emit: &mut HashSet<String>, visits: &mut Visits) {
+        // update visit count
+        visits.record(self);
+        collector.push(self.text[covered..].to_string());
 
         if self.terminal {
             let jo = collector.join("");
             emit.insert(jo);
         }
-        let mut children = self.children.values();
-        while let Some(child) = children.next() {
-            child.get_string_suffixes(false, prefix, collector, emit);
+        for child in self.children.values() {
+            child.string_suffixes(0, collector, emit, visits);
         }
         collector.pop();
     }
-
-    fn visit_count(&self) -> u64 {
-        self.visit_count.load(std::sync::atomic::Ordering::Relaxed)
-    }
 }
 
 #[derive(Debug)]
@@ -641,7 +1032,7 @@ pub enum CharacterSet {
     /// Capitalized are treated like lower cased
     CapitalizedLetters,
 
-    Char(HashSet<char>)
+    Char(BTreeSet<char>)
 
 }
 
@@ -682,7 +1073,7 @@ impl PartialEq for Tagged {
 }
 
 
-/// the originating offset in that string
+/// the originating (byte) offset in that string, always on a char boundary
 type Offset = usize;
 /// the idea is that for a given string you map to the offset for a trimmed character set
 #[derive(Clone,Debug)]
@@ -692,11 +1083,11 @@ struct TaggedString {
 
 impl TaggedString {
     /// tags this string
-    /// example: "abc" => < (a,0), (b,1), (b,2) >
+    /// example: "aéb" => < (a,0), (é,1), (b,3) >
     #[allow(dead_code)]
     fn new(str: &str) -> Self {
         Self {
-            chars: str.chars().enumerate().map(|(offset, char)| {
+            chars: str.char_indices().map(|(offset, char)| {
                 (Tagged::Char(char), offset)
             }).collect()
         }
@@ -705,9 +1096,9 @@ impl TaggedString {
 
 
 /// tags the strings with the offsets
-impl From<Vec<NormalizedChar>> for TaggedString {
-    fn from(chars: Vec<NormalizedChar>) -> Self {
-        let tagged  = chars.into_iter().enumerate().flat_map(|(offset,y)| {
+impl From<Vec<(Offset, NormalizedChar)>> for TaggedString {
+    fn from(chars: Vec<(Offset, NormalizedChar)>) -> Self {
+        let tagged  = chars.into_iter().flat_map(|(offset,y)| {
             match y {
                 NormalizedChar::Squash => {None}
                 NormalizedChar::Char(x) => {Some((Tagged::Char(x), offset))}
@@ -730,11 +1121,10 @@ impl CharacterSet {
             CharacterSet::WhiteSpacesAndNewLines if char == '\n' || char == ' '=> { NormalizedChar::Squash}
             CharacterSet::CapitalizedLetters => { NormalizedChar::Char(char.to_uppercase().next().unwrap_or(char))}
             CharacterSet::Char(x) if x.contains(&char)=> {
-                let mut v = x.iter().collect::<Vec<_>>();
-                v.sort(); // not sure if hash is dependant on ordering
-                let mut s = DefaultHasher::new();
+                // the set is ordered so equal sets always hash the same
+                let mut s = SentinelHasher::default();
 
-                v.hash(&mut s);
+                x.hash(&mut s);
                 NormalizedChar::Sentinal(s.finish(), char)
             }
             _ => NormalizedChar::Char(char)
@@ -742,41 +1132,41 @@ impl CharacterSet {
     }
 }
 
-fn encode(str: &str, treatments: &HashMap<char, CharacterSet>) -> Vec<NormalizedChar> {
-    str.chars().map(|c| {
-            treatments.get(&c).map(|t| t.normalized_char(c)).unwrap_or_else(|| NormalizedChar::Char(c))
-        }
-    ).collect::<Vec<_>>()
-}
+/// FNV-1a hasher for the sentinel values of character sets
+/// unlike the std `DefaultHasher` it is available without std and is stable across platforms and releases
+struct SentinelHasher(u64);
 
-/// since slicing a string by bytes will not work when you have long unicode characters (wide grapheme cluster?)
-/// take chars and allocate a new string (unless you have a way to slice it by cluster???).
-/// TODO: figure out how to return a slice instead of new string
-fn grapheme_slicer_until_end(str: &str, from:usize) -> String {
-    // figure how to slice char boundary and not allocate a new string
-    let temp = str.chars().collect::<Vec<_>>();
-    let x = &temp.as_slice()[from..temp.len()];
-    x.iter().collect()
+impl Default for SentinelHasher {
+    fn default() -> Self {
+        Self(0xcbf29ce484222325)
+    }
 }
 
-fn grapheme_slicer_until_point(str: &str, until: usize) -> String {
+impl Hasher for SentinelHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
 
-    let temp = str.chars().collect::<Vec<_>>();
-    let x = &temp.as_slice()[0..until];
-    x.iter().collect()
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(0x100000001b3);
+        }
+    }
 }
 
-#[test]
-fn test_shitty_slicer() {
-    let text = "🤡abcded🤡";
-    let pref = grapheme_slicer_until_point(text, 4);
-    assert_eq!(pref, "🤡abc");
+/// normalizes every char along with its byte offset
+fn encode(str: &str, treatments: &CharMap<char, CharacterSet>) -> Vec<(Offset, NormalizedChar)> {
+    str.char_indices().map(|(offset, c)| {
+            (offset, treatments.get(&c).map(|t| t.normalized_char(c)).unwrap_or_else(|| NormalizedChar::Char(c)))
+        }
+    ).collect::<Vec<_>>()
 }
 /// describes matching options
 /// you supply a mapping of characters to the character set to match against
-/// for example * matches against all characters
+/// for wildcards such as * (any characters) see `pattern::Pattern`
 pub struct MatchingOptions {
-    treatments: HashMap<char, CharacterSet>, // TODO: need to check if "char" supports emoji and other wide characters
+    treatments: CharMap<char, CharacterSet>, // TODO: need to check if "char" supports emoji and other wide characters
 }
 
 impl MatchingOptions {
@@ -788,7 +1178,7 @@ impl MatchingOptions {
     }
     /// match but accept white space differences
     pub fn ignoring_white_space() -> Self {
-        let mut treatments = HashMap::new();
+        let mut treatments = CharMap::new();
         treatments.insert(' ', CharacterSet::WhiteSpaces);
         treatments.insert('\t', CharacterSet::WhiteSpaces);
         Self {
@@ -798,7 +1188,7 @@ impl MatchingOptions {
 
     /// match but accept diff in new lines
     pub fn ignoring_new_lines() -> Self {
-        let mut treatments = HashMap::new();
+        let mut treatments = CharMap::new();
         treatments.insert('\n', CharacterSet::WhiteSpaces);
         Self {
             treatments
@@ -806,7 +1196,7 @@ impl MatchingOptions {
     }
     /// match but accept new lines and whitespace differences
     pub fn ignoring_white_space_and_new_lines() -> Self {
-        let mut treatments = HashMap::new();
+        let mut treatments = CharMap::new();
         treatments.insert(' ', CharacterSet::WhiteSpaces);
         treatments.insert('\t', CharacterSet::WhiteSpaces);
         treatments.insert('\n', CharacterSet::WhiteSpaces);
@@ -821,18 +1211,46 @@ impl MatchingOptions {
     }
 }
 
+/// removes the child if it no longer holds any key or merges it with its only child to keep the trie compressed
+fn compress_child<V, K: ?Sized + TrieKey>(children: &mut CharMap<K::Symbol, Node<V, K>>, c: K::Symbol,
+                     node_count: &mut usize,
+                     char_count: &mut usize) {
+    let Some(child) = children.get_mut(&c) else {
+        return
+    };
+    if child.terminal {
+        return
+    }
+    match child.children.len() {
+        0 => {
+            // removing dangling child
+            *node_count -= 1;
+            *char_count -= child.text.borrow().key_len();
+            children.remove(&c);
+        }
+        1 => child.merge_with_child(node_count),
+        _ => {}
+    }
+}
+
+/// the length in bytes of the shortest suffix of the prefix which the node text starts with
 fn find_common_overlap_of_prefix_with_node(prefix: &str, node: &str) -> Option<usize>{
     // node - omeabcde
     // prefix rome
     // result o
-    for offset in (0..prefix.len()).rev() {
-        let str = grapheme_slicer_until_end(&prefix, offset);
-        if node.starts_with(str.as_str()) {
-            return Some(str.len())
-        }
-    }
-    None
+    prefix.char_indices()
+        .rev()
+        .map(|(offset, _)| &prefix[offset..])
+        .find(|suffix| node.starts_with(suffix))
+        .map(str::len)
+}
 
+#[test]
+fn test_common_overlap_on_char_boundaries() {
+    assert_eq!(find_common_overlap_of_prefix_with_node("rome", "omeabcde"), Some(3));
+    assert_eq!(find_common_overlap_of_prefix_with_node("🤡ab🤡", "🤡c"), Some(4));
+    assert_eq!(find_common_overlap_of_prefix_with_node("aé", "éa"), Some(2));
+    assert_eq!(find_common_overlap_of_prefix_with_node("abc", "d"), None);
 }
 
 
@@ -849,6 +1267,17 @@ fn test_squashing() {
     println!("{:?}", nt2);
 }
 
+#[test]
+fn test_sentinel_hash_is_stable() {
+    let vowels = CharacterSet::Char(['a', 'e', 'i', 'o', 'u'].into_iter().collect());
+    let reversed = CharacterSet::Char(['u', 'o', 'i', 'e', 'a'].into_iter().collect());
+    let (NormalizedChar::Sentinal(x, 'a'), NormalizedChar::Sentinal(y, 'e')) = (vowels.normalized_char('a'), reversed.normalized_char('e')) else {
+        panic!("vowels should be tagged with a sentinel")
+    };
+    assert_eq!(x, y);
+    assert_eq!(vowels.normalized_char('b'), NormalizedChar::Char('b'));
+}
+
 #[test]
 fn test_empty() {
     let mut trie: Trie<String> = Trie::new();
@@ -0,0 +1,328 @@
+//! the map keyed by symbols which holds the children of every node
+//!
+//! most nodes have a handful of children (and the leaves none at all), so they are kept in a vector
+//! sorted by symbol, which costs no allocation for a leaf, a binary search to look up and gives the
+//! ordered traversal without sorting. once a node has more than `MAX_SORTED` children (the root of a
+//! trie over a big alphabet, say) the vector moves into a `BTreeMap` so inserting stays logarithmic.
+//! a hashed map would not keep the order, and both layouts work without `std`.
+use alloc::collections::btree_map::{self, BTreeMap};
+use alloc::vec::Vec;
+use core::fmt::{Debug, Formatter};
+use core::iter::Map;
+use core::slice;
+
+/// the number of children a sorted vector holds before it becomes a `BTreeMap`
+const MAX_SORTED: usize = 32;
+
+#[derive(Clone)]
+pub(crate) enum CharMap<K, V> {
+    Sorted(Vec<(K, V)>),
+    Tree(BTreeMap<K, V>),
+}
+
+impl<K, V> Default for CharMap<K, V> {
+    fn default() -> Self {
+        CharMap::Sorted(Vec::new())
+    }
+}
+
+impl<K: Debug, V: Debug> Debug for CharMap<K, V> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<K: Ord, V> CharMap<K, V> {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        match self {
+            CharMap::Sorted(entries) => entries.len(),
+            CharMap::Tree(tree) => tree.len(),
+        }
+    }
+
+    pub(crate) fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    pub(crate) fn get(&self, key: &K) -> Option<&V> {
+        match self {
+            CharMap::Sorted(entries) => entries.binary_search_by(|(k, _)| k.cmp(key)).ok().map(|i| &entries[i].1),
+            CharMap::Tree(tree) => tree.get(key),
+        }
+    }
+
+    pub(crate) fn contains_key(&self, key: &K) -> bool {
+        self.get(key).is_some()
+    }
+
+    pub(crate) fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        match self {
+            CharMap::Sorted(entries) => {
+                let i = entries.binary_search_by(|(k, _)| k.cmp(key)).ok()?;
+                Some(&mut entries[i].1)
+            }
+            CharMap::Tree(tree) => tree.get_mut(key),
+        }
+    }
+
+    pub(crate) fn insert(&mut self, key: K, value: V) -> Option<V> {
+        match self.entry(key) {
+            Entry::Occupied(entry) => Some(core::mem::replace(entry.into_mut(), value)),
+            Entry::Vacant(entry) => {
+                entry.insert(value);
+                None
+            }
+        }
+    }
+
+    pub(crate) fn remove(&mut self, key: &K) -> Option<V> {
+        match self {
+            CharMap::Sorted(entries) => {
+                let i = entries.binary_search_by(|(k, _)| k.cmp(key)).ok()?;
+                Some(entries.remove(i).1)
+            }
+            // stays a tree, removing children is rare compared to looking them up
+            CharMap::Tree(tree) => tree.remove(key),
+        }
+    }
+
+    pub(crate) fn entry(&mut self, key: K) -> Entry<'_, K, V> {
+        if let CharMap::Sorted(entries) = self {
+            if entries.len() >= MAX_SORTED && entries.binary_search_by(|(k, _)| k.cmp(&key)).is_err() {
+                *self = CharMap::Tree(core::mem::take(entries).into_iter().collect());
+            }
+        }
+        match self {
+            CharMap::Sorted(entries) => match entries.binary_search_by(|(k, _)| k.cmp(&key)) {
+                Ok(index) => Entry::Occupied(OccupiedEntry::Sorted(&mut entries[index].1)),
+                Err(index) => Entry::Vacant(VacantEntry::Sorted { entries, index, key }),
+            },
+            CharMap::Tree(tree) => match tree.entry(key) {
+                btree_map::Entry::Occupied(entry) => Entry::Occupied(OccupiedEntry::Tree(entry)),
+                btree_map::Entry::Vacant(entry) => Entry::Vacant(VacantEntry::Tree(entry)),
+            },
+        }
+    }
+}
+
+impl<K, V> CharMap<K, V> {
+    /// the entries in the order of their keys
+    pub(crate) fn iter(&self) -> Iter<'_, K, V> {
+        match self {
+            CharMap::Sorted(entries) => Iter::Sorted(entries.iter().map(|(k, v)| (k, v))),
+            CharMap::Tree(tree) => Iter::Tree(tree.iter()),
+        }
+    }
+
+    pub(crate) fn iter_mut(&mut self) -> IterMut<'_, K, V> {
+        match self {
+            CharMap::Sorted(entries) => IterMut::Sorted(entries.iter_mut().map(|(k, v)| (&*k, v))),
+            CharMap::Tree(tree) => IterMut::Tree(tree.iter_mut()),
+        }
+    }
+
+    #[cfg(feature = "tracing")]
+    pub(crate) fn keys(&self) -> impl DoubleEndedIterator<Item = &K> {
+        self.iter().map(|(k, _)| k)
+    }
+
+    pub(crate) fn values(&self) -> impl DoubleEndedIterator<Item = &V> + Clone {
+        self.iter().map(|(_, v)| v)
+    }
+
+    pub(crate) fn values_mut(&mut self) -> impl DoubleEndedIterator<Item = &mut V> {
+        self.iter_mut().map(|(_, v)| v)
+    }
+
+    pub(crate) fn into_values(self) -> impl DoubleEndedIterator<Item = V> {
+        self.into_iter().map(|(_, v)| v)
+    }
+}
+
+pub(crate) enum Entry<'a, K, V> {
+    Occupied(OccupiedEntry<'a, K, V>),
+    Vacant(VacantEntry<'a, K, V>),
+}
+
+pub(crate) enum OccupiedEntry<'a, K, V> {
+    Sorted(&'a mut V),
+    Tree(btree_map::OccupiedEntry<'a, K, V>),
+}
+
+impl<'a, K: Ord, V> OccupiedEntry<'a, K, V> {
+    pub(crate) fn into_mut(self) -> &'a mut V {
+        match self {
+            OccupiedEntry::Sorted(value) => value,
+            OccupiedEntry::Tree(entry) => entry.into_mut(),
+        }
+    }
+}
+
+pub(crate) enum VacantEntry<'a, K, V> {
+    /// there is room in the vector (it was turned into a tree before otherwise)
+    Sorted { entries: &'a mut Vec<(K, V)>, index: usize, key: K },
+    Tree(btree_map::VacantEntry<'a, K, V>),
+}
+
+impl<'a, K: Ord, V> VacantEntry<'a, K, V> {
+    pub(crate) fn insert(self, value: V) -> &'a mut V {
+        match self {
+            VacantEntry::Sorted { entries, index, key } => {
+                entries.insert(index, (key, value));
+                &mut entries[index].1
+            }
+            VacantEntry::Tree(entry) => entry.insert(value),
+        }
+    }
+}
+
+type SortedIter<'a, K, V> = Map<slice::Iter<'a, (K, V)>, fn(&'a (K, V)) -> (&'a K, &'a V)>;
+type SortedIterMut<'a, K, V> = Map<slice::IterMut<'a, (K, V)>, fn(&'a mut (K, V)) -> (&'a K, &'a mut V)>;
+
+/// iterator over the entries of a `CharMap` in the order of their keys
+pub(crate) enum Iter<'a, K, V> {
+    Sorted(SortedIter<'a, K, V>),
+    Tree(btree_map::Iter<'a, K, V>),
+}
+
+// derived it would need `K: Clone, V: Clone`
+impl<K, V> Clone for Iter<'_, K, V> {
+    fn clone(&self) -> Self {
+        match self {
+            Iter::Sorted(iter) => Iter::Sorted(iter.clone()),
+            Iter::Tree(iter) => Iter::Tree(iter.clone()),
+        }
+    }
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            Iter::Sorted(iter) => iter.next(),
+            Iter::Tree(iter) => iter.next(),
+        }
+    }
+}
+
+impl<K, V> DoubleEndedIterator for Iter<'_, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        match self {
+            Iter::Sorted(iter) => iter.next_back(),
+            Iter::Tree(iter) => iter.next_back(),
+        }
+    }
+}
+
+pub(crate) enum IterMut<'a, K, V> {
+    Sorted(SortedIterMut<'a, K, V>),
+    Tree(btree_map::IterMut<'a, K, V>),
+}
+
+impl<'a, K, V> Iterator for IterMut<'a, K, V> {
+    type Item = (&'a K, &'a mut V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            IterMut::Sorted(iter) => iter.next(),
+            IterMut::Tree(iter) => iter.next(),
+        }
+    }
+}
+
+impl<K, V> DoubleEndedIterator for IterMut<'_, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        match self {
+            IterMut::Sorted(iter) => iter.next_back(),
+            IterMut::Tree(iter) => iter.next_back(),
+        }
+    }
+}
+
+pub(crate) enum IntoIter<K, V> {
+    Sorted(alloc::vec::IntoIter<(K, V)>),
+    Tree(btree_map::IntoIter<K, V>),
+}
+
+impl<K, V> Iterator for IntoIter<K, V> {
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            IntoIter::Sorted(iter) => iter.next(),
+            IntoIter::Tree(iter) => iter.next(),
+        }
+    }
+}
+
+impl<K, V> DoubleEndedIterator for IntoIter<K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        match self {
+            IntoIter::Sorted(iter) => iter.next_back(),
+            IntoIter::Tree(iter) => iter.next_back(),
+        }
+    }
+}
+
+impl<K, V> IntoIterator for CharMap<K, V> {
+    type Item = (K, V);
+    type IntoIter = IntoIter<K, V>;
+
+    fn into_iter(self) -> IntoIter<K, V> {
+        match self {
+            CharMap::Sorted(entries) => IntoIter::Sorted(entries.into_iter()),
+            CharMap::Tree(tree) => IntoIter::Tree(tree.into_iter()),
+        }
+    }
+}
+
+/// serialized as a map like before, whatever the layout
+#[cfg(feature = "serde")]
+impl<K: serde::Serialize, V: serde::Serialize> serde::Serialize for CharMap<K, V> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(self.iter())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, K: serde::Deserialize<'de> + Ord, V: serde::Deserialize<'de>> serde::Deserialize<'de> for CharMap<K, V> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct Visitor<K, V>(core::marker::PhantomData<(K, V)>);
+
+        impl<'de, K: serde::Deserialize<'de> + Ord, V: serde::Deserialize<'de>> serde::de::Visitor<'de> for Visitor<K, V> {
+            type Value = CharMap<K, V>;
+
+            fn expecting(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+                f.write_str("a map")
+            }
+
+            fn visit_map<A: serde::de::MapAccess<'de>>(self, mut access: A) -> Result<Self::Value, A::Error> {
+                let mut entries = Vec::with_capacity(access.size_hint().unwrap_or(0).min(MAX_SORTED));
+                while let Some(entry) = access.next_entry()? {
+                    entries.push(entry);
+                }
+                if entries.len() > MAX_SORTED {
+                    return Ok(CharMap::Tree(entries.into_iter().collect()))
+                }
+                entries.sort_by(|(x, _), (y, _)| x.cmp(y));
+                // the last one wins like it would in a map
+                entries.dedup_by(|later, earlier| {
+                    let duplicate = later.0 == earlier.0;
+                    if duplicate {
+                        core::mem::swap(later, earlier);
+                    }
+                    duplicate
+                });
+                Ok(CharMap::Sorted(entries))
+            }
+        }
+
+        deserializer.deserialize_map(Visitor(core::marker::PhantomData))
+    }
+}
+
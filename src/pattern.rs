@@ -0,0 +1,171 @@
+//! glob style patterns (`r?b??n`, `rub*s`, `rom[ae]*`) matched by a guided walk over the trie
+//!
+//! the states of a pattern are the positions in it, a star stays alive for as long as it likes.
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::str::FromStr;
+use crate::automaton::{Automaton, CharClass};
+use crate::{Trie, TrieError};
+
+pub use crate::automaton::Matches;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Char(char),
+    /// `?`
+    Any,
+    /// `*`
+    Star,
+    /// `[ae]`, `[a-z]` or negated `[!ae]`
+    Class(CharClass),
+}
+
+impl Token {
+    fn matches(&self, c: char) -> bool {
+        match self {
+            Token::Char(x) => *x == c,
+            Token::Any | Token::Star => true,
+            Token::Class(class) => class.matches(c),
+        }
+    }
+}
+
+/// a glob style pattern over whole keys
+///
+/// * `?` matches any single char
+/// * `*` matches any (possibly empty) sequence of chars
+/// * `[ae]` matches one of the chars, `[a-z]` a range of them and `[!ae]` (or `[^ae]`) any other char
+/// * `\` matches the next char literally
+///
+/// ```
+/// use ab_radix_trie::Trie;
+/// use ab_radix_trie::pattern::Pattern;
+/// let mut trie: Trie<i32> = Trie::new();
+/// trie.insert("rubens", Some(1));
+/// trie.insert("ruber", Some(2));
+/// trie.insert("rubicon", Some(3));
+/// let pattern = Pattern::new("rub?[nr]*").unwrap();
+/// let keys = trie.matches(&pattern).map(|(key, _)| key).collect::<Vec<_>>();
+/// assert_eq!(keys, vec!["rubens", "ruber"]);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pattern {
+    source: String,
+    tokens: Vec<Token>,
+}
+
+impl Pattern {
+    pub fn new(pattern: &str) -> Result<Self, TrieError> {
+        let mut tokens = Vec::new();
+        let mut chars = pattern.chars().enumerate().peekable();
+        while let Some((position, c)) = chars.next() {
+            let token = match c {
+                '?' => Token::Any,
+                // consecutive stars match the same as one
+                '*' if tokens.last() == Some(&Token::Star) => continue,
+                '*' => Token::Star,
+                '\\' => Token::Char(chars.next().ok_or(TrieError::InvalidPattern { position })?.1),
+                '[' => {
+                    let negated = chars.next_if(|(_, c)| *c == '!' || *c == '^').is_some();
+                    let mut ranges = Vec::new();
+                    loop {
+                        let (_, from) = chars.next().ok_or(TrieError::InvalidPattern { position })?;
+                        if from == ']' && !ranges.is_empty() {
+                            break
+                        }
+                        let to = match chars.next_if(|(_, c)| *c == '-') {
+                            // a trailing - is literal: [a-]
+                            Some((_, '-')) if chars.peek().is_some_and(|(_, c)| *c == ']') => {
+                                ranges.push(('-', '-'));
+                                from
+                            }
+                            Some(_) => chars.next().ok_or(TrieError::InvalidPattern { position })?.1,
+                            None => from,
+                        };
+                        ranges.push((from, to));
+                    }
+                    Token::Class(CharClass { negated, ranges })
+                }
+                c => Token::Char(c),
+            };
+            tokens.push(token);
+        }
+        Ok(Self { source: pattern.to_string(), tokens })
+    }
+
+    pub fn as_str(&self) -> &str {
+        self.source.as_str()
+    }
+
+    /// true if the pattern matches the whole text
+    pub fn is_match(&self, text: &str) -> bool {
+        Automaton::is_match(self, text)
+    }
+
+    /// adds the positions after the stars, as a star may match nothing at all
+    fn closure(&self, mut states: Vec<usize>) -> Vec<usize> {
+        states.sort_unstable();
+        states.dedup();
+        let mut i = 0;
+        while i < states.len() {
+            let position = states[i];
+            if self.tokens.get(position) == Some(&Token::Star) && states.get(i + 1) != Some(&(position + 1)) {
+                states.insert(i + 1, position + 1);
+            }
+            i += 1;
+        }
+        states
+    }
+}
+
+impl Automaton for Pattern {
+    /// the positions (indices into the tokens) alive before reading any char
+    fn start(&self) -> Vec<usize> {
+        self.closure(alloc::vec![0])
+    }
+
+    /// the positions alive after reading one more char
+    fn step(&self, states: &[usize], c: char) -> Vec<usize> {
+        let mut next = Vec::new();
+        for &i in states {
+            match self.tokens.get(i) {
+                // a star may keep on matching
+                Some(Token::Star) => next.push(i),
+                Some(token) if token.matches(c) => next.push(i + 1),
+                _ => {}
+            }
+        }
+        self.closure(next)
+    }
+
+    fn accepts(&self, states: &[usize]) -> bool {
+        states.last() == Some(&self.tokens.len())
+    }
+
+    /// the only chars which can be read next, None if other tokens than literal chars are alive
+    fn next_chars(&self, states: &[usize]) -> Option<Vec<char>> {
+        states.iter()
+            .filter(|&&i| i < self.tokens.len())
+            .map(|&i| match self.tokens[i] {
+                Token::Char(c) => Some(c),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+impl FromStr for Pattern {
+    type Err = TrieError;
+
+    fn from_str(pattern: &str) -> Result<Self, Self::Err> {
+        Pattern::new(pattern)
+    }
+}
+
+impl<V> Trie<V> {
+    /// iterates over the keys matching the pattern (and their values) in lexicographic order
+    /// only the branches which can still match are walked
+    pub fn matches<'p>(&self, pattern: &'p Pattern) -> Matches<'_, 'p, V> {
+        Matches::new(self, pattern)
+    }
+}
@@ -0,0 +1,258 @@
+//! the best k keys under a prefix according to a score, see [`Trie::top_k`]
+//!
+//! every node knows the longest key and the most visited node in its subtree, and where its largest
+//! value is when [`Trie::track_max_values`] is on, which bounds the score of the whole subtree for the
+//! scorers of this module. once k keys were found the subtrees which cannot beat the worst of them are skipped.
+use alloc::collections::BinaryHeap;
+use alloc::vec::Vec;
+use core::borrow::Borrow;
+use core::cmp::{Ordering, Reverse};
+use crate::iter::stack_order;
+use crate::{Node, Trie, TrieKey};
+
+/// scores the keys for [`Trie::top_k`], higher is better
+///
+/// any `Fn(&K, Option<&V>) -> Option<S>` is a scorer (without pruning), the key is left out when it returns None
+pub trait Scorer<V, K: ?Sized + TrieKey = str> {
+    type Score<'a>: Ord where V: 'a, K: 'a;
+
+    /// the score of the key ending at the (terminal) node, None leaves the key out
+    fn score<'a>(&self, key: &K, node: &'a Node<V, K>) -> Option<Self::Score<'a>>;
+
+    /// an upper bound of the scores in the subtree of the node, given the length (in symbols) of the key leading up to it
+    /// None when the subtree cannot be bounded, it is always searched then
+    fn bound<'a>(&self, key_len: usize, node: &'a Node<V, K>) -> Option<Self::Score<'a>> {
+        let _ = (key_len, node);
+        None
+    }
+}
+
+impl<V, K: ?Sized + TrieKey, S: Ord, F: Fn(&K, Option<&V>) -> Option<S>> Scorer<V, K> for F {
+    type Score<'a> = S where V: 'a, K: 'a;
+
+    fn score(&self, key: &K, node: &Node<V, K>) -> Option<S> {
+        self(key, node.value())
+    }
+}
+
+/// ranks the keys by their value, keys without a value are left out
+///
+/// the subtrees are only bounded while [`Trie::track_max_values`] is on, otherwise every key under the prefix is scored
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ByValue;
+
+impl<V: Ord, K: ?Sized + TrieKey> Scorer<V, K> for ByValue {
+    type Score<'a> = &'a V where V: 'a, K: 'a;
+
+    fn score<'a>(&self, _key: &K, node: &'a Node<V, K>) -> Option<&'a V> {
+        node.value()
+    }
+
+    fn bound<'a>(&self, _key_len: usize, node: &'a Node<V, K>) -> Option<&'a V> {
+        node.max_value()
+    }
+}
+
+/// where the largest value in the subtree of a node is, only kept up to date while `Trie::track_max_values` is on
+///
+/// a node is marked stale when a value below it may change, and so are all the nodes above it:
+/// a node which is not stale has no stale node below it
+#[derive(Debug, Clone, Default)]
+pub(crate) enum MaxValue<S> {
+    #[default]
+    Stale,
+    /// there is no value in the subtree
+    Nothing,
+    /// the value of the node itself
+    Here,
+    /// the largest value of the child under the symbol
+    Below(S),
+}
+
+impl<V, K: ?Sized + TrieKey> Node<V, K> {
+    /// finds the largest value again in the stale nodes of the subtree
+    fn refresh_max_value(&mut self, order: fn(&V, &V) -> Ordering) {
+        if !matches!(self.max_value_at, MaxValue::Stale) {
+            return
+        }
+        for child in self.children.values_mut() {
+            child.refresh_max_value(order);
+        }
+        // the node itself wins the ties, it comes first
+        let mut max = self.value.as_ref().map(|value| (MaxValue::Here, value));
+        for (symbol, child) in self.children.iter() {
+            let Some(value) = child.max_value() else {
+                continue
+            };
+            if max.as_ref().is_some_and(|(_, max)| order(value, max).is_le()) {
+                continue
+            }
+            max = Some((MaxValue::Below(symbol.clone()), value));
+        }
+        self.max_value_at = max.map_or(MaxValue::Nothing, |(at, _)| at);
+    }
+}
+
+impl<V: Ord, K: ?Sized + TrieKey> Trie<V, K> {
+    /// keeps where the largest value of every subtree is, so `top_k` with [`ByValue`] skips the subtrees which cannot
+    /// make it (off by default). turning it on walks over the trie
+    ///
+    /// `insert` and `remove` keep it up to date. a value changed in place (through `get_mut`, `iter_mut` or an entry)
+    /// leaves the nodes above it unbounded until the next `insert` or `remove`, their subtrees are searched until then
+    pub fn track_max_values(&mut self, enabled: bool) {
+        self.value_order = enabled.then_some(V::cmp);
+        self.refresh_max_values();
+    }
+}
+
+impl<V, K: ?Sized + TrieKey> Trie<V, K> {
+    pub fn is_tracking_max_values(&self) -> bool {
+        self.value_order.is_some()
+    }
+
+    /// finds the largest values again in the stale nodes, if they are tracked
+    pub(crate) fn refresh_max_values(&mut self) {
+        let Some(order) = self.value_order else {
+            return
+        };
+        for node in self.children.values_mut().chain(self.empty.as_mut()) {
+            node.refresh_max_value(order);
+        }
+    }
+}
+
+/// ranks the keys by how often their node was visited, see [`Trie::track_visits`]
+///
+/// visits which were not merged yet are not included (see [`Trie::merge_visits`])
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ByVisitCount;
+
+impl<V, K: ?Sized + TrieKey> Scorer<V, K> for ByVisitCount {
+    type Score<'a> = u64 where V: 'a, K: 'a;
+
+    fn score(&self, _key: &K, node: &Node<V, K>) -> Option<u64> {
+        Some(node.visit_count())
+    }
+
+    fn bound(&self, _key_len: usize, node: &Node<V, K>) -> Option<u64> {
+        Some(node.max_visit_count())
+    }
+}
+
+/// ranks the keys by their length in symbols (chars for strings), longest first
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ByKeyLength;
+
+impl<V, K: ?Sized + TrieKey> Scorer<V, K> for ByKeyLength {
+    type Score<'a> = usize where V: 'a, K: 'a;
+
+    fn score(&self, key: &K, _node: &Node<V, K>) -> Option<usize> {
+        Some(key.symbol_count())
+    }
+
+    fn bound(&self, key_len: usize, node: &Node<V, K>) -> Option<usize> {
+        Some(key_len + node.max_key_len())
+    }
+}
+
+/// a key found by `top_k`, ties go to the key found first (the smaller one)
+struct Candidate<'a, S, V, O> {
+    score: S,
+    found: usize,
+    key: O,
+    value: Option<&'a V>,
+}
+
+impl<S: Ord, V, O> Candidate<'_, S, V, O> {
+    fn rank(&self) -> (&S, Reverse<usize>) {
+        (&self.score, Reverse(self.found))
+    }
+}
+
+impl<S: Ord, V, O> PartialEq for Candidate<'_, S, V, O> {
+    fn eq(&self, other: &Self) -> bool {
+        self.rank() == other.rank()
+    }
+}
+
+impl<S: Ord, V, O> Eq for Candidate<'_, S, V, O> {}
+
+impl<S: Ord, V, O> PartialOrd for Candidate<'_, S, V, O> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<S: Ord, V, O> Ord for Candidate<'_, S, V, O> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.rank().cmp(&other.rank())
+    }
+}
+
+impl<V, K: ?Sized + TrieKey> Trie<V, K> {
+    /// returns the k keys starting with the prefix which score best, best first
+    /// keys with the same score are returned in lexicographic order
+    /// ```
+    /// use ab_radix_trie::Trie;
+    /// use ab_radix_trie::rank::ByValue;
+    /// let mut trie: Trie<u32> = Trie::new();
+    /// trie.insert("romane", Some(3));
+    /// trie.insert("romanus", Some(7));
+    /// trie.insert("romulus", Some(5));
+    /// trie.insert("rubens", Some(9));
+    /// let best = trie.top_k("rom", 2, ByValue);
+    /// assert_eq!(best, vec![("romanus".to_string(), Some(&7)), ("romulus".to_string(), Some(&5))]);
+    /// ```
+    pub fn top_k<S: Scorer<V, K>>(&self, prefix: &K, k: usize, scorer: S) -> Vec<(K::Owned, Option<&V>)> {
+        if k == 0 {
+            return Vec::new()
+        }
+        let (mut key, roots) = if prefix.key_len() == 0 {
+            let mut roots = stack_order(&self.children);
+            // the empty key comes before everything else
+            roots.extend(self.empty.as_ref());
+            (K::Owned::default(), roots)
+        } else {
+            match self.prefix_root(prefix) {
+                Some((consumed, node)) => (prefix.split_key_at(consumed).0.to_owned_key(), alloc::vec![node]),
+                None => return Vec::new(),
+            }
+        };
+        let key_symbols = key.borrow().symbol_count();
+        // nodes still to visit with the length of the key leading up to them in units and symbols
+        let mut stack = roots.into_iter().map(|node| (node, key.borrow().key_len(), key_symbols)).collect::<Vec<_>>();
+        // min heap holding the best k so far, the worst one on top
+        let mut best: BinaryHeap<Reverse<Candidate<S::Score<'_>, V, _>>> = BinaryHeap::with_capacity(k + 1);
+        let mut found = 0;
+        while let Some((node, len, chars)) = stack.pop() {
+            if let (true, Some(Reverse(worst))) = (best.len() == k, best.peek()) {
+                // keys of the subtree cannot beat the worst one, a tie goes to the worst one as it was found before
+                if scorer.bound(chars, node).is_some_and(|bound| bound <= worst.score) {
+                    continue
+                }
+            }
+            K::truncate_key(&mut key, len);
+            K::push_key(&mut key, node.text.borrow());
+            let (len, chars) = (key.borrow().key_len(), chars + node.text.borrow().symbol_count());
+            stack.extend(stack_order(&node.children).into_iter().map(|child| (child, len, chars)));
+            if !node.terminal {
+                continue
+            }
+            let Some(score) = scorer.score(key.borrow(), node) else {
+                continue
+            };
+            found += 1;
+            if best.len() == k && best.peek().is_some_and(|Reverse(worst)| score <= worst.score) {
+                continue
+            }
+            best.push(Reverse(Candidate { score, found, key: key.clone(), value: node.value() }));
+            if best.len() > k {
+                best.pop();
+            }
+        }
+        best.into_sorted_vec()
+            .into_iter()
+            .map(|Reverse(candidate)| (candidate.key, candidate.value))
+            .collect()
+    }
+}
@@ -0,0 +1,443 @@
+//! regular expressions over whole keys, run in step with the walk over the trie
+//!
+//! the expression is compiled into a small nfa (thompson construction). like the `fst` crate does
+//! it, the set of alive nfa states is advanced char by char along the edges, so the keys are never
+//! scanned one by one and only the branches which can still match are walked.
+//!
+//! supported syntax:
+//! * literal chars, `.` (any char) and escapes of the meta chars (`\.`, `\*`, ...), `\n`, `\r`, `\t`
+//! * classes `[a-z_]`, negated classes `[^0-9]` and the shorthands `\d`, `\w`, `\s` (`\D`, `\W`, `\S` negated)
+//! * groups `(...)` and `(?:...)`, alternation `|`
+//! * repetition `*`, `+`, `?`, `{n}`, `{n,}` and `{n,m}`
+//! * `^` at the start and `$` at the end, which change nothing as a match always spans the whole key
+//!
+//! a counted repetition is a copy of the repeated expression, so their bounds are limited to
+//! 1000 and the whole compiled expression to 10000 instructions, which nested repetitions reach quickly.
+//! groups and repetitions nest at most 256 levels deep, as parsing and compiling recurse once per level.
+use alloc::boxed::Box;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::iter::{Enumerate, Peekable};
+use core::str::{Chars, FromStr};
+use crate::automaton::{Automaton, CharClass};
+use crate::{Trie, TrieError};
+
+pub use crate::automaton::Matches;
+
+/// the largest bound of a counted repetition, every repetition is a copy of the repeated nfa
+const MAX_REPEAT: usize = 1000;
+/// the most instructions an expression compiles to, every step of the walk allocates as many flags
+const MAX_INSTS: usize = 10_000;
+/// the most groups and repetitions on a path down the expression, parsing and compiling recurse once per level
+const MAX_DEPTH: usize = 256;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Matcher {
+    Char(char),
+    Any,
+    Class(CharClass),
+}
+
+impl Matcher {
+    fn matches(&self, c: char) -> bool {
+        match self {
+            Matcher::Char(x) => *x == c,
+            Matcher::Any => true,
+            Matcher::Class(class) => class.matches(c),
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Ast {
+    Empty,
+    Atom(Matcher),
+    Concat(Vec<Ast>),
+    Alternation(Vec<Ast>),
+    Repeat { ast: Box<Ast>, min: usize, max: Option<usize> },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Inst {
+    /// reads a matching char and continues at the next instruction
+    Consume(Matcher, usize),
+    /// continues at both instructions without reading a char
+    Split(usize, usize),
+    Jump(usize),
+    Match,
+}
+
+/// a regular expression matching whole keys, see the [module](self) for the syntax
+///
+/// ```
+/// use ab_radix_trie::Trie;
+/// use ab_radix_trie::regex::Regex;
+/// let mut trie: Trie<()> = Trie::new();
+/// for key in ["user_id", "user_name", "userId", "_tmp1"] {
+///     trie.insert(key, None);
+/// }
+/// let snake_case = Regex::new("[a-z]+(_[a-z]+)*").unwrap();
+/// let keys = trie.matches_regex(&snake_case).map(|(key, _)| key).collect::<Vec<_>>();
+/// assert_eq!(keys, vec!["user_id", "user_name"]);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Regex {
+    source: String,
+    insts: Vec<Inst>,
+}
+
+impl Regex {
+    pub fn new(regex: &str) -> Result<Self, TrieError> {
+        let mut parser = Parser { chars: regex.chars().enumerate().peekable(), len: regex.chars().count(), depth: 0 };
+        let (ast, _) = parser.alternation()?;
+        if let Some((position, _)) = parser.chars.next() {
+            // only an unbalanced ) stops the parser early
+            return Err(TrieError::InvalidRegex { position })
+        }
+        // the repetitions were checked by the parser, this is a long expression without any
+        if size(&ast) >= MAX_INSTS {
+            return Err(TrieError::InvalidRegex { position: 0 })
+        }
+        let mut insts = Vec::new();
+        compile(&ast, &mut insts);
+        insts.push(Inst::Match);
+        Ok(Self { source: regex.to_string(), insts })
+    }
+
+    pub fn as_str(&self) -> &str {
+        self.source.as_str()
+    }
+
+    /// true if the expression matches the whole text
+    pub fn is_match(&self, text: &str) -> bool {
+        Automaton::is_match(self, text)
+    }
+
+    /// adds the instruction and everything reachable from it without reading a char
+    fn add(&self, pc: usize, states: &mut Vec<usize>, seen: &mut [bool]) {
+        let mut pending = vec![pc];
+        while let Some(pc) = pending.pop() {
+            if core::mem::replace(&mut seen[pc], true) {
+                continue
+            }
+            match self.insts[pc] {
+                Inst::Split(x, y) => pending.extend([y, x]),
+                Inst::Jump(x) => pending.push(x),
+                Inst::Consume(..) | Inst::Match => states.push(pc),
+            }
+        }
+    }
+}
+
+impl Automaton for Regex {
+    fn start(&self) -> Vec<usize> {
+        let mut states = Vec::new();
+        self.add(0, &mut states, &mut vec![false; self.insts.len()]);
+        states
+    }
+
+    fn step(&self, states: &[usize], c: char) -> Vec<usize> {
+        let mut next = Vec::new();
+        let mut seen = vec![false; self.insts.len()];
+        for &pc in states {
+            if let Inst::Consume(matcher, to) = &self.insts[pc] {
+                if matcher.matches(c) {
+                    self.add(*to, &mut next, &mut seen);
+                }
+            }
+        }
+        next
+    }
+
+    fn accepts(&self, states: &[usize]) -> bool {
+        states.iter().any(|&pc| self.insts[pc] == Inst::Match)
+    }
+
+    fn next_chars(&self, states: &[usize]) -> Option<Vec<char>> {
+        states.iter()
+            .filter_map(|&pc| match &self.insts[pc] {
+                Inst::Consume(Matcher::Char(c), _) => Some(Some(*c)),
+                Inst::Consume(..) => Some(None),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+impl FromStr for Regex {
+    type Err = TrieError;
+
+    fn from_str(regex: &str) -> Result<Self, Self::Err> {
+        Regex::new(regex)
+    }
+}
+
+impl<V> Trie<V> {
+    /// iterates over the keys matched (as a whole) by the regular expression, in lexicographic order
+    /// only the branches which can still match are walked
+    pub fn matches_regex<'r>(&self, regex: &'r Regex) -> Matches<'_, 'r, V> {
+        Matches::new(self, regex)
+    }
+}
+
+struct Parser<'s> {
+    chars: Peekable<Enumerate<Chars<'s>>>,
+    /// in chars, to tell whether a $ is the last one
+    len: usize,
+    /// the groups open around the current char
+    depth: usize,
+}
+
+/// an expression along with its depth, the most groups and repetitions on a path down the expression
+type Nested = (Ast, usize);
+
+impl Parser<'_> {
+    fn alternation(&mut self) -> Result<Nested, TrieError> {
+        let (first, mut depth) = self.concatenation()?;
+        let mut branches = vec![first];
+        while self.chars.next_if(|(_, c)| *c == '|').is_some() {
+            let (branch, branch_depth) = self.concatenation()?;
+            depth = depth.max(branch_depth);
+            branches.push(branch);
+        }
+        Ok((if branches.len() == 1 { branches.pop().unwrap() } else { Ast::Alternation(branches) }, depth))
+    }
+
+    fn concatenation(&mut self) -> Result<Nested, TrieError> {
+        let mut items = Vec::new();
+        let mut depth = 0;
+        while let Some(&(position, c)) = self.chars.peek() {
+            if c == '|' || c == ')' {
+                break
+            }
+            self.chars.next();
+            let mut atom_depth = 0;
+            let atom = match c {
+                '(' => {
+                    if self.chars.next_if(|(_, c)| *c == '?').is_some() && self.chars.next_if(|(_, c)| *c == ':').is_none() {
+                        return Err(TrieError::InvalidRegex { position })
+                    }
+                    if self.depth == MAX_DEPTH {
+                        return Err(TrieError::InvalidRegex { position })
+                    }
+                    self.depth += 1;
+                    let (group, group_depth) = self.alternation()?;
+                    self.depth -= 1;
+                    if self.chars.next_if(|(_, c)| *c == ')').is_none() || group_depth == MAX_DEPTH {
+                        return Err(TrieError::InvalidRegex { position })
+                    }
+                    atom_depth = group_depth + 1;
+                    group
+                }
+                '.' => Ast::Atom(Matcher::Any),
+                '[' => Ast::Atom(Matcher::Class(self.class(position)?)),
+                '\\' => Ast::Atom(self.escape(position)?),
+                '^' if position == 0 => Ast::Empty,
+                '$' if position + 1 == self.len => Ast::Empty,
+                '*' | '+' | '?' | '{' | '^' | '$' => return Err(TrieError::InvalidRegex { position }),
+                c => Ast::Atom(Matcher::Char(c)),
+            };
+            let (item, item_depth) = self.repetitions(atom, atom_depth)?;
+            depth = depth.max(item_depth);
+            items.push(item);
+        }
+        let ast = match items.len() {
+            0 => Ast::Empty,
+            1 => items.pop().unwrap(),
+            _ => Ast::Concat(items),
+        };
+        Ok((ast, depth))
+    }
+
+    /// `depth` is the depth of `ast`, every repetition adds one
+    fn repetitions(&mut self, mut ast: Ast, mut depth: usize) -> Result<Nested, TrieError> {
+        while let Some(&(position, c)) = self.chars.peek() {
+            if matches!(c, '*' | '+' | '?' | '{') {
+                if depth == MAX_DEPTH {
+                    return Err(TrieError::InvalidRegex { position })
+                }
+                depth += 1;
+            }
+            let (min, max) = match c {
+                '*' => (0, None),
+                '+' => (1, None),
+                '?' => (0, Some(1)),
+                '{' => {
+                    self.chars.next();
+                    let min = self.number().ok_or(TrieError::InvalidRegex { position })?;
+                    let max = match self.chars.next() {
+                        Some((_, '}')) => Some(min),
+                        Some((_, ',')) if self.chars.next_if(|(_, c)| *c == '}').is_some() => None,
+                        Some((_, ',')) => {
+                            let max = self.number().filter(|max| *max >= min);
+                            let max = max.ok_or(TrieError::InvalidRegex { position })?;
+                            self.chars.next_if(|(_, c)| *c == '}').ok_or(TrieError::InvalidRegex { position })?;
+                            Some(max)
+                        }
+                        _ => return Err(TrieError::InvalidRegex { position }),
+                    };
+                    if max.unwrap_or(min) > MAX_REPEAT {
+                        return Err(TrieError::InvalidRegex { position })
+                    }
+                    ast = Ast::Repeat { ast: Box::new(ast), min, max };
+                    if size(&ast) >= MAX_INSTS {
+                        return Err(TrieError::InvalidRegex { position })
+                    }
+                    continue
+                }
+                _ => break,
+            };
+            self.chars.next();
+            ast = Ast::Repeat { ast: Box::new(ast), min, max };
+        }
+        Ok((ast, depth))
+    }
+
+    fn number(&mut self) -> Option<usize> {
+        let mut number: Option<usize> = None;
+        while let Some((_, digit)) = self.chars.next_if(|(_, c)| c.is_ascii_digit()) {
+            let digit = digit.to_digit(10).unwrap() as usize;
+            number = Some(number.unwrap_or(0).saturating_mul(10).saturating_add(digit));
+        }
+        number
+    }
+
+    /// the class after a [ at the position
+    fn class(&mut self, position: usize) -> Result<CharClass, TrieError> {
+        let negated = self.chars.next_if(|(_, c)| *c == '^').is_some();
+        let mut ranges = Vec::new();
+        let mut first = true;
+        loop {
+            let (at, from) = self.chars.next().ok_or(TrieError::InvalidRegex { position })?;
+            match from {
+                // a ] right after the [ is literal
+                ']' if !first => break,
+                '\\' => match self.escape(at)? {
+                    Matcher::Char(c) => ranges.push((c, self.range_end(c, position)?)),
+                    Matcher::Class(class) if !class.negated => ranges.extend(class.ranges),
+                    _ => return Err(TrieError::InvalidRegex { position: at }),
+                },
+                from => ranges.push((from, self.range_end(from, position)?)),
+            }
+            first = false;
+        }
+        Ok(CharClass { negated, ranges })
+    }
+
+    /// the end of a range in a class starting at `from`, or `from` itself if it is not a range
+    fn range_end(&mut self, from: char, position: usize) -> Result<char, TrieError> {
+        let is_range = self.chars.peek().is_some_and(|(_, c)| *c == '-')
+            && self.chars.clone().nth(1).is_some_and(|(_, c)| c != ']');
+        if !is_range {
+            return Ok(from)
+        }
+        self.chars.next();
+        let to = match self.chars.next() {
+            Some((at, '\\')) => match self.escape(at)? {
+                Matcher::Char(c) => c,
+                _ => return Err(TrieError::InvalidRegex { position: at }),
+            },
+            Some((_, to)) => to,
+            None => return Err(TrieError::InvalidRegex { position }),
+        };
+        if to < from {
+            return Err(TrieError::InvalidRegex { position })
+        }
+        Ok(to)
+    }
+
+    /// the escape after a \ at the position
+    fn escape(&mut self, position: usize) -> Result<Matcher, TrieError> {
+        let (_, c) = self.chars.next().ok_or(TrieError::InvalidRegex { position })?;
+        let class = |negated, ranges: &[(char, char)]| Matcher::Class(CharClass { negated, ranges: ranges.to_vec() });
+        let digits = [('0', '9')];
+        let word = [('0', '9'), ('A', 'Z'), ('_', '_'), ('a', 'z')];
+        let space = [('\t', '\r'), (' ', ' ')];
+        Ok(match c {
+            'n' => Matcher::Char('\n'),
+            'r' => Matcher::Char('\r'),
+            't' => Matcher::Char('\t'),
+            'd' | 'D' => class(c == 'D', &digits),
+            'w' | 'W' => class(c == 'W', &word),
+            's' | 'S' => class(c == 'S', &space),
+            c if c.is_alphanumeric() => return Err(TrieError::InvalidRegex { position }),
+            c => Matcher::Char(c),
+        })
+    }
+}
+
+/// the number of instructions `compile` appends for the expression
+fn size(ast: &Ast) -> usize {
+    match ast {
+        Ast::Empty => 0,
+        Ast::Atom(_) => 1,
+        Ast::Concat(items) => items.iter().map(size).fold(0, usize::saturating_add),
+        // a split and a jump for every branch but the last one
+        Ast::Alternation(branches) => branches.iter().map(size).fold(2 * (branches.len() - 1), usize::saturating_add),
+        Ast::Repeat { ast, min, max } => {
+            let size = size(ast);
+            let optional = match max {
+                None => size.saturating_add(2),
+                Some(max) => (max - min).saturating_mul(size.saturating_add(1)),
+            };
+            min.saturating_mul(size).saturating_add(optional)
+        }
+    }
+}
+
+/// appends the instructions of the expression, ending up at the instruction after them
+fn compile(ast: &Ast, insts: &mut Vec<Inst>) {
+    match ast {
+        Ast::Empty => {}
+        Ast::Atom(matcher) => insts.push(Inst::Consume(matcher.clone(), insts.len() + 1)),
+        Ast::Concat(items) => {
+            for item in items {
+                compile(item, insts);
+            }
+        }
+        Ast::Alternation(branches) => {
+            let mut jumps = Vec::new();
+            let (last, rest) = branches.split_last().unwrap();
+            for branch in rest {
+                let split = insts.len();
+                insts.push(Inst::Split(split + 1, 0));
+                compile(branch, insts);
+                jumps.push(insts.len());
+                insts.push(Inst::Jump(0));
+                insts[split] = Inst::Split(split + 1, insts.len());
+            }
+            compile(last, insts);
+            let end = insts.len();
+            for jump in jumps {
+                insts[jump] = Inst::Jump(end);
+            }
+        }
+        Ast::Repeat { ast, min, max } => {
+            for _ in 0..*min {
+                compile(ast, insts);
+            }
+            match max {
+                None => {
+                    let split = insts.len();
+                    insts.push(Inst::Split(split + 1, 0));
+                    compile(ast, insts);
+                    insts.push(Inst::Jump(split));
+                    insts[split] = Inst::Split(split + 1, insts.len());
+                }
+                Some(max) => {
+                    let mut splits = Vec::new();
+                    for _ in *min..*max {
+                        splits.push(insts.len());
+                        insts.push(Inst::Split(0, 0));
+                        compile(ast, insts);
+                    }
+                    let end = insts.len();
+                    for split in splits {
+                        insts[split] = Inst::Split(split + 1, end);
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,239 @@
+//! the structural invariants of a trie, see [`Trie::validate`]
+use alloc::vec::Vec;
+use core::borrow::Borrow;
+use core::cmp::Ordering;
+use core::fmt::{Debug, Display, Formatter};
+use crate::map::CharMap;
+use crate::rank::MaxValue;
+use crate::{Node, Trie, TrieKey};
+
+/// an invariant a trie breaks, `key` is the accumulated text of the node at fault (up to and including its own)
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum InvariantViolation<P> {
+    /// a node below the root level has no text
+    EmptyText { key: P },
+    /// a node is not stored under the first symbol of its text
+    ChildKeyMismatch { key: P },
+    /// the children of a node (or of the root level, with an empty key) are not in the order of their symbols,
+    /// they are looked up by binary search
+    UnsortedChildren { key: P },
+    /// a node which is not a key has a single child, they should have been merged
+    Uncompressed { key: P },
+    /// a node which is not a key has no children, it should have been removed
+    Dangling { key: P },
+    /// a node which is not a key holds a value
+    ValueWithoutKey { key: P },
+    /// the node of the empty key has text or children, or is not a key
+    EmptyKeyNode,
+    /// the weight of a node is not the length of its text and the texts below it
+    Weight { key: P, stored: usize, expected: usize },
+    /// the length of the longest key below a node is off
+    MaxKeyLength { key: P, stored: usize, expected: usize },
+    /// the largest visit count below a node is off
+    MaxVisits { key: P, stored: u64, expected: u64 },
+    /// the largest value below a node is not the one kept, or a node below it is stale without it being stale
+    /// (only checked while the largest values are tracked)
+    MaxValue { key: P },
+    NodeCount { stored: usize, expected: usize },
+    CharCount { stored: usize, expected: usize },
+    KeyCount { stored: usize, expected: usize },
+}
+
+impl<P: Debug> Display for InvariantViolation<P> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            InvariantViolation::EmptyText { key } => write!(f, "the node at {:?} has no text", key),
+            InvariantViolation::ChildKeyMismatch { key } => write!(f, "the node at {:?} is stored under another symbol", key),
+            InvariantViolation::UnsortedChildren { key } => write!(f, "the children of the node at {:?} are not sorted", key),
+            InvariantViolation::Uncompressed { key } => write!(f, "the node at {:?} is not a key and has a single child", key),
+            InvariantViolation::Dangling { key } => write!(f, "the node at {:?} is not a key and has no children", key),
+            InvariantViolation::ValueWithoutKey { key } => write!(f, "the node at {:?} is not a key but has a value", key),
+            InvariantViolation::EmptyKeyNode => write!(f, "the node of the empty key is malformed"),
+            InvariantViolation::Weight { key, stored, expected } => write!(f, "the node at {:?} weighs {} instead of {}", key, stored, expected),
+            InvariantViolation::MaxKeyLength { key, stored, expected } => write!(f, "the node at {:?} bounds the key length by {} instead of {}", key, stored, expected),
+            InvariantViolation::MaxVisits { key, stored, expected } => write!(f, "the node at {:?} bounds the visit count by {} instead of {}", key, stored, expected),
+            InvariantViolation::MaxValue { key } => write!(f, "the node at {:?} does not bound its values", key),
+            InvariantViolation::NodeCount { stored, expected } => write!(f, "the node count is {} instead of {}", stored, expected),
+            InvariantViolation::CharCount { stored, expected } => write!(f, "the char count is {} instead of {}", stored, expected),
+            InvariantViolation::KeyCount { stored, expected } => write!(f, "the key count is {} instead of {}", stored, expected),
+        }
+    }
+}
+
+impl<P: Debug> core::error::Error for InvariantViolation<P> {}
+
+/// what is counted while walking the nodes
+#[derive(Default)]
+struct Counts {
+    nodes: usize,
+    chars: usize,
+    keys: usize,
+}
+
+impl<V, K: ?Sized + TrieKey> Trie<V, K> {
+    /// checks the structure of the trie and the counters kept along with it, this walks over every node
+    /// returns every violation found, meant for debug builds and for tries which were deserialized
+    ///
+    /// ```
+    /// use ab_radix_trie::Trie;
+    /// let mut trie: Trie<i32> = Trie::new();
+    /// trie.insert("romanus", Some(1));
+    /// trie.insert("romulus", Some(2));
+    /// trie.remove("romanus");
+    /// assert_eq!(trie.validate(), Ok(()));
+    /// ```
+    pub fn validate(&self) -> Result<(), Vec<InvariantViolation<K::Owned>>> {
+        let mut violations = Vec::new();
+        let mut counts = Counts::default();
+        if let Some(empty) = &self.empty {
+            if empty.text.borrow().key_len() != 0 || empty.children.len() != 0 || !empty.terminal {
+                violations.push(InvariantViolation::EmptyKeyNode);
+            }
+            counts.keys += 1;
+        }
+        let mut key = K::Owned::default();
+        if !is_sorted(&self.children) {
+            violations.push(InvariantViolation::UnsortedChildren { key: key.clone() });
+        }
+        for (symbol, child) in self.children.iter() {
+            validate_node(child, symbol, &mut key, &mut counts, &mut violations);
+        }
+        if let Some(order) = self.value_order {
+            for node in self.children.values().chain(&self.empty) {
+                validate_max_value(node, &mut key, order, &mut violations);
+            }
+        }
+        if self.node_count != counts.nodes {
+            violations.push(InvariantViolation::NodeCount { stored: self.node_count, expected: counts.nodes });
+        }
+        if self.char_count != counts.chars {
+            violations.push(InvariantViolation::CharCount { stored: self.char_count, expected: counts.chars });
+        }
+        if self.key_count != counts.keys {
+            violations.push(InvariantViolation::KeyCount { stored: self.key_count, expected: counts.keys });
+        }
+        if violations.is_empty() { Ok(()) } else { Err(violations) }
+    }
+}
+
+/// a `BTreeMap` keeps its own order, only the sorted vector can be out of order
+fn is_sorted<S: Ord, N>(children: &CharMap<S, N>) -> bool {
+    match children {
+        CharMap::Sorted(entries) => entries.windows(2).all(|pair| pair[0].0 < pair[1].0),
+        CharMap::Tree(_) => true,
+    }
+}
+
+/// checks the node and everything below it, `key` leads up to the node
+/// returns the weight, the longest key length and the largest visit count the node should have
+fn validate_node<V, K: ?Sized + TrieKey>(node: &Node<V, K>, symbol: &K::Symbol, key: &mut K::Owned,
+                                         counts: &mut Counts, violations: &mut Vec<InvariantViolation<K::Owned>>) -> (usize, usize, u64) {
+    let len = Borrow::<K>::borrow(key).key_len();
+    let text: &K = node.text.borrow();
+    K::push_key(key, text);
+    counts.nodes += 1;
+    counts.chars += text.key_len();
+    counts.keys += node.terminal as usize;
+    match text.first_symbol() {
+        None => violations.push(InvariantViolation::EmptyText { key: key.clone() }),
+        Some(first) if first != *symbol => violations.push(InvariantViolation::ChildKeyMismatch { key: key.clone() }),
+        _ => {}
+    }
+    if !node.terminal {
+        match node.children.len() {
+            0 => violations.push(InvariantViolation::Dangling { key: key.clone() }),
+            1 => violations.push(InvariantViolation::Uncompressed { key: key.clone() }),
+            _ => {}
+        }
+        if node.value.is_some() {
+            violations.push(InvariantViolation::ValueWithoutKey { key: key.clone() });
+        }
+    }
+    if !is_sorted(&node.children) {
+        violations.push(InvariantViolation::UnsortedChildren { key: key.clone() });
+    }
+    let (mut weight, mut max_len, mut max_visits) = (0, 0, node.visit_count);
+    for (symbol, child) in node.children.iter() {
+        let (child_weight, child_max_len, child_max_visits) = validate_node(child, symbol, key, counts, violations);
+        weight += child_weight;
+        max_len = max_len.max(child_max_len);
+        max_visits = max_visits.max(child_max_visits);
+    }
+    let weight = text.key_len() + weight;
+    let max_len = text.symbol_count() + max_len;
+    if node.weight != weight {
+        violations.push(InvariantViolation::Weight { key: key.clone(), stored: node.weight, expected: weight });
+    }
+    if node.max_len != max_len {
+        violations.push(InvariantViolation::MaxKeyLength { key: key.clone(), stored: node.max_len, expected: max_len });
+    }
+    if node.max_visits != max_visits {
+        violations.push(InvariantViolation::MaxVisits { key: key.clone(), stored: node.max_visits, expected: max_visits });
+    }
+    K::truncate_key(key, len);
+    (weight, max_len, max_visits)
+}
+
+/// checks the largest values kept in the subtree of the node, `key` leads up to the node
+/// returns the largest value of the subtree and whether it has a stale node
+fn validate_max_value<'a, V, K: ?Sized + TrieKey>(node: &'a Node<V, K>, key: &mut K::Owned, order: fn(&V, &V) -> Ordering,
+                                                  violations: &mut Vec<InvariantViolation<K::Owned>>) -> (Option<&'a V>, bool) {
+    let len = Borrow::<K>::borrow(key).key_len();
+    K::push_key(key, node.text.borrow());
+    let (mut max, mut stale) = (node.value.as_ref(), false);
+    for child in node.children.values() {
+        let (child_max, child_stale) = validate_max_value(child, key, order, violations);
+        if let Some(child_max) = child_max {
+            max = Some(max.map_or(child_max, |max| core::cmp::max_by(max, child_max, |a, b| order(a, b))));
+        }
+        stale |= child_stale;
+    }
+    let kept = match node.max_value_at {
+        MaxValue::Stale => None,
+        _ => Some(node.max_value()),
+    };
+    let bounded = match kept {
+        None => true,
+        Some(kept) => !stale && match (kept, max) {
+            (Some(kept), Some(max)) => order(kept, max).is_eq(),
+            (kept, max) => kept.is_none() && max.is_none(),
+        },
+    };
+    if !bounded {
+        violations.push(InvariantViolation::MaxValue { key: key.clone() });
+    }
+    K::truncate_key(key, len);
+    (max, stale || kept.is_none())
+}
+
+#[test]
+fn test_bounds_and_order() {
+    let mut trie: Trie<i32> = Trie::new();
+    for key in ["romanus", "romulus", "rubens"] {
+        trie.insert(key, None);
+    }
+    let rom = trie.children.get_mut(&'r').unwrap().children.get_mut(&'o').unwrap();
+    rom.visit_count = 3;
+    if let CharMap::Sorted(children) = &mut rom.children {
+        children.reverse();
+    }
+    assert_eq!(trie.validate(), Err(alloc::vec![
+        InvariantViolation::UnsortedChildren { key: "rom".into() },
+        InvariantViolation::MaxVisits { key: "rom".into(), stored: 0, expected: 3 },
+        InvariantViolation::MaxVisits { key: "r".into(), stored: 0, expected: 3 },
+    ]));
+
+    let mut trie: Trie<i32> = Trie::new();
+    trie.track_max_values(true);
+    for (key, value) in [("romanus", 1), ("romulus", 2), ("rubens", 3)] {
+        trie.insert(key, Some(value));
+    }
+    // "rom" keeps the smaller of its values, "ub" turns stale below the (not stale) "r"
+    trie.children.get_mut(&'r').unwrap().children.get_mut(&'o').unwrap().max_value_at = MaxValue::Below('a');
+    trie.children.get_mut(&'r').unwrap().children.get_mut(&'u').unwrap().max_value_at = MaxValue::Stale;
+    assert_eq!(trie.validate(), Err(alloc::vec![
+        InvariantViolation::MaxValue { key: "rom".into() },
+        InvariantViolation::MaxValue { key: "r".into() },
+    ]));
+}
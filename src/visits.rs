@@ -0,0 +1,201 @@
+//! optional bookkeeping of how often the nodes are visited by read only queries
+//!
+//! read only queries only have `&Trie`, so instead of writing to the nodes they log the visited
+//! nodes and add the log to the counts of their thread once the query is done. the log is a buffer
+//! of the thread reused by its next query, and only the thread adds to its counts.
+//! the counts of all the threads are merged into `Node::visit_count` (and `Node::max_visit_count`)
+//! on demand (`Trie::merge_visits`) and before any change to the structure of the trie, as they are
+//! kept by node address. when tracking is off (the default) queries do not write anything.
+use alloc::vec::Vec;
+use crate::{Node, Trie, TrieKey};
+
+/// the nodes visited by a single query, identified by their address
+pub(crate) struct Visits(Option<Vec<usize>>);
+
+impl Visits {
+    pub(crate) fn off() -> Self {
+        Visits(None)
+    }
+
+    pub(crate) fn record<V, K: ?Sized + TrieKey>(&mut self, node: &Node<V, K>) {
+        if let Some(log) = &mut self.0 {
+            log.push(node as *const Node<V, K> as usize);
+        }
+    }
+
+    /// hands the log of a finished query to the tracker, if tracking is on
+    pub(crate) fn record_into(self, tracker: &Option<VisitTracker>) {
+        let (Some(tracker), Some(log)) = (tracker, self.0) else {
+            return
+        };
+        tracker.add(log);
+    }
+}
+
+#[cfg(feature = "std")]
+pub(crate) use tracker::VisitTracker;
+
+/// without std there are no per-thread counts to record into, so tracking cannot be turned on
+#[cfg(not(feature = "std"))]
+#[derive(Debug)]
+pub(crate) enum VisitTracker {}
+
+#[cfg(not(feature = "std"))]
+impl VisitTracker {
+    fn log(&self) -> Vec<usize> {
+        match *self {}
+    }
+
+    fn add(&self, _log: Vec<usize>) {
+        match *self {}
+    }
+}
+
+#[cfg(feature = "std")]
+mod tracker {
+    use std::cell::{Cell, RefCell};
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex, PoisonError, Weak};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// the visit counts per node address of one thread. only that thread adds to them, so the lock is
+    /// not contended: the only other one taking it is `Trie::merge_visits`, which has the trie to itself
+    type Counts = Mutex<HashMap<usize, u64>>;
+
+    #[derive(Debug)]
+    pub(crate) struct VisitTracker {
+        id: usize,
+        /// the counts of every thread which recorded visits, a thread registers the first time it does
+        threads: Mutex<Vec<Arc<Counts>>>,
+    }
+
+    static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+    std::thread_local! {
+        /// the counts of this thread by the id of the tracker they belong to
+        static COUNTS: RefCell<Vec<(usize, Weak<Counts>)>> = const { RefCell::new(Vec::new()) };
+        /// the log of the last query of this thread, emptied
+        static LOG: Cell<Vec<usize>> = const { Cell::new(Vec::new()) };
+    }
+
+    impl VisitTracker {
+        pub(crate) fn new() -> Self {
+            VisitTracker {
+                id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+                threads: Mutex::new(Vec::new()),
+            }
+        }
+
+        /// an empty log for a query, the buffer of the previous query of the thread if there is one
+        pub(crate) fn log(&self) -> Vec<usize> {
+            LOG.take()
+        }
+
+        /// adds the log of a finished query to the counts of this thread and keeps its buffer
+        pub(crate) fn add(&self, mut log: Vec<usize>) {
+            if !log.is_empty() {
+                let counts = self.counts_of_this_thread();
+                let mut counts = counts.lock().unwrap_or_else(PoisonError::into_inner);
+                for address in log.drain(..) {
+                    *counts.entry(address).or_default() += 1;
+                }
+            }
+            LOG.set(log);
+        }
+
+        fn counts_of_this_thread(&self) -> Arc<Counts> {
+            COUNTS.with_borrow_mut(|threads| {
+                if let Some(counts) = threads.iter().find(|(id, _)| *id == self.id).and_then(|(_, counts)| counts.upgrade()) {
+                    return counts
+                }
+                // the counts of the trackers dropped since
+                threads.retain(|(_, counts)| counts.strong_count() > 0);
+                let counts = Arc::new(Counts::default());
+                threads.push((self.id, Arc::downgrade(&counts)));
+                self.threads.lock().unwrap_or_else(PoisonError::into_inner).push(counts.clone());
+                counts
+            })
+        }
+
+        /// takes all the pending visits out of the counts of the threads
+        pub(crate) fn drain(&mut self) -> HashMap<usize, u64> {
+            let threads = self.threads.get_mut().unwrap_or_else(PoisonError::into_inner);
+            let mut merged: HashMap<usize, u64> = HashMap::new();
+            for counts in threads.iter() {
+                let mut counts = counts.lock().unwrap_or_else(PoisonError::into_inner);
+                for (address, count) in counts.drain() {
+                    *merged.entry(address).or_default() += count;
+                }
+            }
+            // the threads which have exited do not record anymore
+            threads.retain(|counts| Arc::weak_count(counts) > 0);
+            merged
+        }
+    }
+}
+
+impl<V, K: ?Sized + TrieKey> Trie<V, K> {
+    /// starts the visit log of a read only query
+    pub(crate) fn visits(&self) -> Visits {
+        match &self.visit_tracker {
+            Some(tracker) => Visits(Some(tracker.log())),
+            None => Visits::off(),
+        }
+    }
+
+    /// hands the visit log of a finished query to the tracker
+    pub(crate) fn record_visits(&self, visits: Visits) {
+        visits.record_into(&self.visit_tracker)
+    }
+
+    /// turns counting of node visits by queries on or off (off by default)
+    /// pending visits are merged into the nodes when tracking is turned off
+    #[cfg(feature = "std")]
+    pub fn track_visits(&mut self, enabled: bool) {
+        match (enabled, self.visit_tracker.is_some()) {
+            (true, false) => self.visit_tracker = Some(VisitTracker::new()),
+            (false, true) => {
+                self.merge_visits();
+                self.visit_tracker = None;
+            }
+            _ => {}
+        }
+    }
+
+    pub fn is_tracking_visits(&self) -> bool {
+        self.visit_tracker.is_some()
+    }
+
+    /// adds the visits recorded by queries since the last merge to `Node::visit_count`
+    /// this walks over the trie if there are pending visits
+    pub fn merge_visits(&mut self) {
+        let Some(tracker) = &mut self.visit_tracker else {
+            return
+        };
+        #[cfg(not(feature = "std"))]
+        match *tracker {}
+        #[cfg(feature = "std")]
+        {
+            let mut pending = tracker.drain();
+            if pending.is_empty() {
+                return
+            }
+            let mut stack = self.children.values_mut().collect::<Vec<_>>();
+            while !pending.is_empty() {
+                let Some(node) = stack.pop() else {
+                    break
+                };
+                if let Some(count) = pending.remove(&(node as *const Node<V, K> as usize)) {
+                    node.visit_count += count;
+                }
+                stack.extend(node.children.values_mut());
+            }
+            // every change to the structure merges first, so the visits cannot be of a node freed since (whose
+            // address could have been reused by a new node)
+            debug_assert!(pending.is_empty(), "visits pending for nodes not in the trie: {pending:?}");
+            for child in self.children.values_mut() {
+                child.refresh_max_visits();
+            }
+        }
+    }
+}
@@ -1,5 +1,6 @@
 use std::collections::HashSet;
 use ab_radix_trie::Trie;
+use ab_radix_trie::rank::ByKeyLength;
 
 #[test]
 fn basic_tests() {
@@ -85,4 +86,986 @@ fn test_fuzzy() {
     let results = trie.get_suffixes_with_matching_options("roma", &MatchingOptions::ignoring_white_space());
     // should return "romanus" and "rom anus"
     println!("{:?}", results);
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_fuzzy_all_candidates() {
+    use ab_radix_trie::MatchingOptions;
+    let mut trie: Trie<String> = Trie::new();
+    trie.insert("romanus", None);
+    trie.insert("rom anus", None);
+
+    // exploring every branch finds both, with their full keys
+    trie.insert("romulus", None);
+    trie.insert("rom  an", None);
+    let keys = |prefix: &str, options: &MatchingOptions| {
+        trie.get_keys_with_matching_options(prefix, options).into_iter().map(|entry| entry.key).collect::<Vec<_>>()
+    };
+    let ignoring_white_space = MatchingOptions::ignoring_white_space();
+    assert_eq!(keys("roma", &ignoring_white_space), vec!["rom  an", "rom anus", "romanus"]);
+    assert_eq!(keys("r o m a n u", &ignoring_white_space), vec!["rom anus", "romanus"]);
+    assert_eq!(keys("roma", &MatchingOptions::exact()), vec!["romanus"]);
+    assert_eq!(keys("rom ", &MatchingOptions::exact()), vec!["rom  an", "rom anus"]);
+    assert_eq!(keys("", &ignoring_white_space).len(), trie.len());
+    assert!(keys("romax", &ignoring_white_space).is_empty());
+
+    // the nodes below the matched ones count as visited, like they do for the other queries
+    #[cfg(feature = "std")]
+    {
+        let mut tracked = trie.clone();
+        tracked.track_visits(true);
+        tracked.get_keys_with_matching_options("rom", &MatchingOptions::exact());
+        tracked.merge_visits();
+        trie.track_visits(true);
+        trie.get_suffixes_values("rom");
+        trie.merge_visits();
+        for key in ["rom anus", "romanus", "romulus"] {
+            assert_eq!(tracked.suffix_tree(key).unwrap().visit_count(), trie.suffix_tree(key).unwrap().visit_count(), "{}", key);
+        }
+        assert_eq!(tracked.suffix_tree("romanus").unwrap().visit_count(), 1);
+    }
+}
+
+#[test]
+fn test_exact_lookup() {
+    let mut trie: Trie<i32> = Trie::new();
+    trie.insert("romanus", None);
+    trie.insert("romulus", Some(10));
+    trie.insert("rubens", Some(1));
+    trie.insert("ruber", Some(2));
+    trie.insert("🤡abc", Some(3));
+
+    assert_eq!(trie.get("romulus"), Some(&10));
+    assert_eq!(trie.get("rubens"), Some(&1));
+    assert_eq!(trie.get("ruber"), Some(&2));
+    assert_eq!(trie.get("🤡abc"), Some(&3));
+    // inserted without a value
+    assert_eq!(trie.get("romanus"), None);
+    assert!(trie.contains_key("romanus"));
+
+    // internal (non terminal) nodes and partial keys are not keys
+    assert!(!trie.contains_key("rom"));
+    assert!(!trie.contains_key("rube"));
+    assert!(!trie.contains_key("romulusx"));
+    assert!(!trie.contains_key("🤡"));
+    assert!(!trie.contains_key(""));
+    assert_eq!(trie.get("rub"), None);
+
+    *trie.get_mut("ruber").unwrap() += 40;
+    assert_eq!(trie.get("ruber"), Some(&42));
+    assert!(trie.get_mut("rube").is_none());
+}
+
+#[test]
+fn test_insert_and_remove_return_previous_value() {
+    let mut trie: Trie<i32> = Trie::new();
+    assert_eq!(trie.insert("romulus", Some(1)), None);
+    assert_eq!(trie.insert("romulus", Some(2)), Some(1));
+    assert_eq!(trie.get("romulus"), Some(&2));
+
+    // a key ending in the middle of an existing node
+    assert_eq!(trie.insert("rom", Some(3)), None);
+    assert_eq!(trie.get("rom"), Some(&3));
+    assert_eq!(trie.get("romulus"), Some(&2));
+
+    trie.insert_if_absent("rom", Some(4));
+    assert_eq!(trie.get("rom"), Some(&3));
+    trie.insert_if_absent("romanus", Some(5));
+    assert_eq!(trie.get("romanus"), Some(&5));
+
+    assert_eq!(trie.remove("ro"), None);
+    assert_eq!(trie.remove("rom"), Some(3));
+    assert_eq!(trie.remove("rom"), None);
+    assert!(!trie.contains_key("rom"));
+    assert_eq!(trie.get("romanus"), Some(&5));
+    assert_eq!(trie.remove("romulus"), Some(2));
+    assert_eq!(trie.remove("romanus"), Some(5));
+    assert!(trie.get_suffixes_values("r").is_none());
+}
+
+#[test]
+fn test_entry() {
+    use ab_radix_trie::entry::Entry;
+    let mut trie: Trie<Vec<usize>> = Trie::new();
+    for (position, word) in ["ruber", "rubens", "rub", "ruber", "romulus"].iter().enumerate() {
+        trie.entry(word).or_default().push(position);
+    }
+    assert_eq!(trie.get("ruber"), Some(&vec![0, 3]));
+    assert_eq!(trie.get("rubens"), Some(&vec![1]));
+    assert_eq!(trie.get("rub"), Some(&vec![2]));
+    assert_eq!(trie.get("romulus"), Some(&vec![4]));
+
+    // a key inserted without a value is occupied but holds nothing
+    trie.insert("rubicon", None);
+    match trie.entry("rubicon") {
+        Entry::Occupied(entry) => assert!(entry.get().is_none()),
+        Entry::Vacant(_) => panic!("rubicon was inserted"),
+    }
+    trie.entry("rubicon").and_modify(|v| v.push(100)).or_insert_with(|| vec![5]);
+    assert_eq!(trie.get("rubicon"), Some(&vec![5]));
+    trie.entry("rubicon").and_modify(|v| v.push(6)).or_insert_with(|| vec![100]);
+    assert_eq!(trie.get("rubicon"), Some(&vec![5, 6]));
+
+    match trie.entry("rubi") {
+        Entry::Occupied(_) => panic!("rubi was never inserted"),
+        Entry::Vacant(entry) => assert_eq!(entry.key(), "rubi"),
+    }
+    assert!(!trie.contains_key("rubi"));
+
+    let results = trie.get_suffixes_values("rub").unwrap();
+    assert_eq!(results.len(), 4);
+
+    // vacant keys are inserted from where the walk stopped, splitting nodes like insert does
+    let mut inserted = trie.clone();
+    for word in ["rubicundus", "rubi", "ru", "r", "rubx", "🤡", "🤡é", ""] {
+        trie.entry(word).or_default().push(7);
+        inserted.insert(word, Some(vec![7]));
+        assert_eq!(trie.validate(), Ok(()), "after {word}");
+    }
+    assert_eq!(trie.stats(), inserted.stats());
+    assert_eq!(trie.iter().collect::<Vec<_>>(), inserted.iter().collect::<Vec<_>>());
+}
+
+#[test]
+fn test_ordered_iteration() {
+    let mut trie: Trie<i32> = Trie::new();
+    for (value, word) in ["rubicundus", "romulus", "ruber", "rom", "rubicon", "romanus", "rubens", "🤡", "a"].iter().enumerate() {
+        trie.insert(word, Some(value as i32));
+    }
+    trie.insert("rubi", None);
+
+    let keys = trie.keys().collect::<Vec<_>>();
+    assert_eq!(keys, vec!["a", "rom", "romanus", "romulus", "rubens", "ruber", "rubi", "rubicon", "rubicundus", "🤡"]);
+    let values = trie.values().copied().collect::<Vec<_>>();
+    assert_eq!(values, vec![8, 3, 5, 1, 6, 2, 4, 0, 7]);
+
+    let rub = trie.iter_prefix("rubi").map(|(key, value)| (key, value.copied())).collect::<Vec<_>>();
+    assert_eq!(rub, vec![("rubi".to_string(), None), ("rubicon".to_string(), Some(4)), ("rubicundus".to_string(), Some(0))]);
+    let rub = trie.iter_prefix("rube").map(|(key, _)| key).collect::<Vec<_>>();
+    assert_eq!(rub, vec!["rubens", "ruber"]);
+    let rom = trie.iter_prefix("roma").map(|(key, _)| key).collect::<Vec<_>>();
+    assert_eq!(rom, vec!["romanus"]);
+    assert_eq!(trie.iter_prefix("romx").count(), 0);
+    assert_eq!(trie.iter_prefix("x").count(), 0);
+    assert_eq!(trie.iter_prefix("").count(), 10);
+
+    for (_, value) in &mut trie {
+        if let Some(value) = value {
+            *value *= 10;
+        }
+    }
+    assert_eq!(trie.get("romulus"), Some(&10));
+
+    let owned = trie.into_iter().collect::<Vec<_>>();
+    assert_eq!(owned.len(), 10);
+    assert_eq!(owned[0], ("a".to_string(), Some(80)));
+    assert_eq!(owned[6], ("rubi".to_string(), None));
+}
+
+#[test]
+fn test_longest_prefix_match() {
+    let mut trie: Trie<&str> = Trie::new();
+    trie.insert("/", Some("root"));
+    trie.insert("/api", Some("api"));
+    trie.insert("/api/v1/users", Some("users"));
+    trie.insert("/api/v1/user", None);
+    trie.insert("/api/v2", Some("v2"));
+
+    assert_eq!(trie.longest_prefix_match("/api/v1/users/42"), Some(("/api/v1/users", Some(&"users"))));
+    assert_eq!(trie.longest_prefix_match("/api/v1/users"), Some(("/api/v1/users", Some(&"users"))));
+    assert_eq!(trie.longest_prefix_match("/api/v1/use"), Some(("/api", Some(&"api"))));
+    assert_eq!(trie.longest_prefix_match("/api/v1/userz"), Some(("/api/v1/user", None)));
+    assert_eq!(trie.longest_prefix_match("/static/app.js"), Some(("/", Some(&"root"))));
+    assert_eq!(trie.longest_prefix_match("api"), None);
+    assert_eq!(trie.longest_prefix_match(""), None);
+
+    let prefixes = trie.prefixes_of("/api/v1/users/42").into_iter().map(|(key, _)| key).collect::<Vec<_>>();
+    assert_eq!(prefixes, vec!["/", "/api", "/api/v1/user", "/api/v1/users"]);
+    assert!(trie.prefixes_of("api").is_empty());
+}
+
+#[test]
+fn test_len_and_stats() {
+    let mut trie: Trie<i32> = Trie::new();
+    assert!(trie.is_empty());
+    trie.insert("romanus", None);
+    trie.insert("romulus", Some(10));
+    trie.insert("rubens", None);
+    trie.insert("ruber", None);
+    trie.insert("rubicon", None);
+    trie.insert("rubicundus", None);
+    trie.insert("ruber", Some(1));
+    trie.insert_if_absent("ruber", Some(2));
+    *trie.entry("rubicon").or_insert(0) += 1;
+    *trie.entry("rub").or_insert(0) += 1;
+    assert_eq!(trie.len(), 7);
+
+    let stats = trie.stats();
+    // r -> om -> (anus, ulus), ub -> (e -> (ns, r), ic -> (on, undus))
+    assert_eq!(stats.node_count, 11);
+    assert_eq!(stats.char_count, 26);
+    assert_eq!(stats.key_count, 7);
+    assert_eq!(stats.max_depth, 4);
+    assert_eq!(stats.branching_factors.get(&0), Some(&6));
+    assert_eq!(stats.branching_factors.get(&2), Some(&5));
+    assert!((stats.average_edge_length - 26.0 / 11.0).abs() < f64::EPSILON);
+
+    trie.remove("rom");
+    trie.remove("romanus");
+    assert_eq!(trie.len(), 6);
+    trie.remove("rub");
+    assert_eq!(trie.len(), 5);
+
+    trie.clear();
+    assert!(trie.is_empty());
+    assert_eq!(trie.stats(), Default::default());
+    assert!(trie.get_suffixes_values("r").is_none());
+}
+
+#[test]
+fn test_empty_key() {
+    use ab_radix_trie::{MatchingOptions, TrieError};
+    let mut trie: Trie<i32> = Trie::new();
+    assert_eq!(trie.remove(""), None);
+    assert!(trie.suffix_tree_with_matching_options("", &MatchingOptions::ignoring_white_space()).is_none());
+    assert_eq!(trie.try_suffix_tree("").unwrap_err(), TrieError::EmptyPrefix);
+    assert_eq!(
+        trie.try_get_suffixes_with_matching_options("", &MatchingOptions::exact()).unwrap_err(),
+        TrieError::EmptyPrefix
+    );
+
+    trie.insert("romulus", Some(1));
+    assert!(!trie.contains_key(""));
+    assert_eq!(trie.insert("", Some(0)), None);
+    assert_eq!(trie.len(), 2);
+    assert_eq!(trie.get(""), Some(&0));
+    assert_eq!(trie.keys().collect::<Vec<_>>(), vec!["", "romulus"]);
+    assert_eq!(trie.longest_prefix_match("rom"), Some(("", Some(&0))));
+    assert_eq!(trie.try_get_suffixes_values("rom").unwrap().unwrap().len(), 1);
+
+    assert_eq!(trie.remove(""), Some(0));
+    assert_eq!(trie.len(), 1);
+    assert_eq!(trie.remove(""), None);
+    assert_eq!(trie.keys().collect::<Vec<_>>(), vec!["romulus"]);
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn test_visit_tracking() {
+    let mut trie: Trie<i32> = Trie::new();
+    trie.insert("romanus", None);
+    trie.insert("romulus", Some(10));
+    trie.insert("rubens", None);
+    assert!(!trie.is_tracking_visits());
+
+    // nothing is counted while tracking is off
+    trie.get("romulus");
+    trie.merge_visits();
+    assert_eq!(trie.suffix_tree("rom").unwrap().visit_count(), 0);
+
+    trie.track_visits(true);
+    std::thread::scope(|scope| {
+        for _ in 0..4 {
+            scope.spawn(|| {
+                for _ in 0..100 {
+                    assert_eq!(trie.get("romulus"), Some(&10));
+                }
+            });
+        }
+    });
+    trie.merge_visits();
+    // "r" -> "om" -> "ulus"
+    assert_eq!(trie.suffix_tree("rom").unwrap().visit_count(), 400);
+    assert_eq!(trie.suffix_tree("romul").unwrap().visit_count(), 400);
+
+    // pending visits are merged before the structure changes
+    trie.get("romanus");
+    trie.insert("roman", None);
+    let roman = trie.suffix_tree("roman").unwrap();
+    assert_eq!(roman.visit_count(), 1);
+
+    // the suffix_tree("romul") query above counted as well
+    trie.track_visits(false);
+    trie.get("romulus");
+    trie.merge_visits();
+    assert_eq!(trie.suffix_tree("romul").unwrap().visit_count(), 401);
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn test_visits_of_freed_nodes() {
+    let mut trie: Trie<i32> = Trie::new();
+    // the pending visits are of nodes freed right after, whose addresses the new nodes may be given
+    for _ in 0..100 {
+        trie.track_visits(true);
+        trie.insert("romulus", None);
+        trie.get("romulus");
+        trie.remove("romulus");
+        trie.insert("rubens", None);
+        trie.get("romulus");
+        trie.clear();
+        trie.insert("rubicon", None);
+        // merges, the check below does not count
+        trie.track_visits(false);
+        assert_eq!(trie.suffix_tree("rubicon").unwrap().visit_count(), 0);
+        trie.clear();
+    }
+}
+
+#[test]
+fn test_top_k() {
+    use ab_radix_trie::rank::{ByKeyLength, ByValue};
+    let mut trie: Trie<u32> = Trie::new();
+    trie.insert("romane", Some(3));
+    trie.insert("romanus", Some(7));
+    trie.insert("romulus", Some(5));
+    trie.insert("rubens", Some(9));
+    trie.insert("ruber", None);
+    trie.insert("rubicon", Some(1));
+    trie.insert("rubicundus", Some(2));
+
+    let keys = |ranked: Vec<(String, Option<&u32>)>| ranked.into_iter().map(|(key, _)| key).collect::<Vec<_>>();
+    assert_eq!(keys(trie.top_k("", 3, ByValue)), vec!["rubens", "romanus", "romulus"]);
+    // keys without a value are left out, k larger than the number of keys is fine
+    assert_eq!(keys(trie.top_k("rub", 10, ByValue)), vec!["rubens", "rubicundus", "rubicon"]);
+    assert_eq!(keys(trie.top_k("rubi", 1, ByKeyLength)), vec!["rubicundus"]);
+    // ties go to the smaller key
+    assert_eq!(keys(trie.top_k("r", 2, ByKeyLength)), vec!["rubicundus", "romanus"]);
+    assert_eq!(keys(trie.top_k("rom", 0, ByValue)), Vec::<String>::new());
+    assert_eq!(keys(trie.top_k("rox", 3, ByValue)), Vec::<String>::new());
+    // any closure scores, here the shortest keys first
+    let shortest = trie.top_k("ru", 2, |key: &str, _: Option<&u32>| Some(std::cmp::Reverse(key.len())));
+    assert_eq!(keys(shortest), vec!["ruber", "rubens"]);
+
+    // the bounds follow removals
+    trie.remove("rubicundus");
+    assert_eq!(keys(trie.top_k("", 1, ByKeyLength)), vec!["romanus"]);
+    assert_eq!(trie.suffix_tree("r").unwrap().max_key_len(), 7);
+}
+
+#[test]
+fn test_top_k_by_value_bound() {
+    use std::cell::Cell;
+    use ab_radix_trie::Node;
+    use ab_radix_trie::rank::{ByValue, Scorer};
+
+    /// counts the keys `ByValue` scores
+    struct Counted<'c>(&'c Cell<usize>);
+
+    impl Scorer<u32> for Counted<'_> {
+        type Score<'a> = &'a u32;
+
+        fn score<'a>(&self, key: &str, node: &'a Node<u32>) -> Option<&'a u32> {
+            self.0.set(self.0.get() + 1);
+            ByValue.score(key, node)
+        }
+
+        fn bound<'a>(&self, key_len: usize, node: &'a Node<u32>) -> Option<&'a u32> {
+            ByValue.bound(key_len, node)
+        }
+    }
+
+    let mut trie: Trie<u32> = Trie::new();
+    // "a" is found first and none of the keys below "b" and "c" can beat it
+    trie.insert("a", Some(100));
+    for i in 0..50 {
+        trie.insert(&format!("b{i}"), Some(i));
+        trie.insert(&format!("c{i}"), Some(i));
+    }
+    let scored = Cell::new(0);
+    let best = |trie: &Trie<u32>, k| {
+        scored.set(0);
+        trie.top_k("", k, Counted(&scored)).into_iter().map(|(key, value)| (key, *value.unwrap())).collect::<Vec<_>>()
+    };
+    assert_eq!(best(&trie, 1), vec![("a".to_string(), 100)]);
+    assert_eq!(scored.get(), 101);
+
+    trie.track_max_values(true);
+    assert_eq!(trie.validate(), Ok(()));
+    assert_eq!(trie.suffix_tree("b").unwrap().max_value(), Some(&49));
+    assert_eq!(best(&trie, 1), vec![("a".to_string(), 100)]);
+    assert_eq!(scored.get(), 1);
+    assert_eq!(best(&trie, 2), vec![("a".to_string(), 100), ("b49".to_string(), 49)]);
+    // "c" is skipped as its largest value only ties with "b49", which was found first, and so are some of the
+    // subtrees of "b" once the two best keys so far beat their values
+    assert_eq!(scored.get(), 46);
+
+    // a value changed in place leaves its subtree unbounded until the next insert or remove
+    *trie.get_mut("c7").unwrap() = 200;
+    assert_eq!(trie.suffix_tree("c").unwrap().max_value(), None);
+    assert_eq!(best(&trie, 1), vec![("c7".to_string(), 200)]);
+    assert_eq!(trie.validate(), Ok(()));
+    trie.insert("d", None);
+    assert_eq!(trie.suffix_tree("c").unwrap().max_value(), Some(&200));
+    *trie.entry("b3").or_insert(0) = 300;
+    for (_, value) in trie.iter_mut() {
+        if let Some(value) = value {
+            *value += 1;
+        }
+    }
+    assert_eq!(best(&trie, 2), vec![("b3".to_string(), 301), ("c7".to_string(), 201)]);
+    trie.remove("b3");
+    assert_eq!(trie.validate(), Ok(()));
+    assert_eq!(best(&trie, 1), vec![("c7".to_string(), 201)]);
+    // "a", "c7" and "d", which has no value to bound it by
+    assert_eq!(scored.get(), 3);
+    assert_eq!(trie.suffix_tree("b").unwrap().max_value(), Some(&50));
+
+    // the largest values are kept through splits and merges
+    let mut trie: Trie<u32> = Trie::new();
+    trie.track_max_values(true);
+    trie.insert("romulus", Some(5));
+    trie.insert("romanus", Some(7));
+    trie.insert("rom", Some(3));
+    trie.insert("", Some(1));
+    assert_eq!(trie.suffix_tree("r").unwrap().max_value(), Some(&7));
+    assert_eq!(trie.validate(), Ok(()));
+    trie.remove("romanus");
+    trie.remove("rom");
+    assert_eq!(trie.suffix_tree("r").unwrap().max_value(), Some(&5));
+    assert_eq!(trie.validate(), Ok(()));
+    trie.remove("romulus");
+    assert_eq!(trie.top_k("", 2, ByValue), vec![(String::new(), Some(&1))]);
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn test_top_k_by_visit_count() {
+    use ab_radix_trie::rank::ByVisitCount;
+    let mut trie: Trie<u32> = Trie::new();
+    for key in ["romane", "romanus", "romulus", "rubens", "ruber"] {
+        trie.insert(key, None);
+    }
+    trie.track_visits(true);
+    for (key, times) in [("ruber", 3), ("romulus", 5), ("romane", 1)] {
+        for _ in 0..times {
+            trie.get(key);
+        }
+    }
+    trie.merge_visits();
+    let ranked = trie.top_k("", 2, ByVisitCount).into_iter().map(|(key, _)| key).collect::<Vec<_>>();
+    assert_eq!(ranked, vec!["romulus", "ruber"]);
+    // "rom" was passed by the queries of both "romulus" and "romane"
+    assert_eq!(trie.suffix_tree("rom").unwrap().max_visit_count(), 6);
+    assert_eq!(trie.suffix_tree("romul").unwrap().max_visit_count(), 5);
+
+    // a new node starts with the visits of the node it was added under, its bound included
+    let mut trie: Trie<u32> = Trie::new();
+    trie.track_visits(true);
+    trie.insert("a", None);
+    trie.insert("b", None);
+    trie.get("a");
+    for _ in 0..5 {
+        trie.get("b");
+    }
+    trie.merge_visits();
+    trie.insert("bc", None);
+    trie.merge_visits();
+    assert_eq!(trie.suffix_tree("bc").unwrap().max_visit_count(), 5);
+    let ranked = trie.top_k("", 2, ByVisitCount).into_iter().map(|(key, _)| key).collect::<Vec<_>>();
+    assert_eq!(ranked, vec!["b", "bc"]);
+    assert_eq!(trie.validate(), Ok(()));
+
+    // every access through a mutable reference counts once
+    let mut trie: Trie<u32> = Trie::new();
+    trie.track_visits(true);
+    trie.insert("romulus", Some(1));
+    trie.get("romulus");
+    trie.merge_visits();
+    assert_eq!(trie.suffix_tree("romulus").unwrap().visit_count(), 1);
+    *trie.entry("romulus").or_insert(0) += 1;
+    *trie.get_mut("romulus").unwrap() += 1;
+    trie.merge_visits();
+    // and the suffix_tree query above
+    assert_eq!(trie.suffix_tree("romulus").unwrap().visit_count(), 4);
+}
+
+#[test]
+fn test_fuzzy_search() {
+    let mut trie: Trie<i32> = Trie::new();
+    for key in ["romane", "romanus", "romulus", "rubens", "ruber", "rubicon", "rubicundus"] {
+        trie.insert(key, None);
+    }
+    trie.insert("rubiconia", Some(1));
+    let hits = |hits: Vec<ab_radix_trie::levenshtein::FuzzyMatch<i32>>| {
+        hits.into_iter().map(|hit| (hit.key, hit.distance)).collect::<Vec<_>>()
+    };
+    let expected = |pairs: &[(&str, usize)]| pairs.iter().map(|(key, d)| (key.to_string(), *d)).collect::<Vec<_>>();
+
+    assert_eq!(hits(trie.fuzzy_search("ruben", 1)), expected(&[("rubens", 1), ("ruber", 1)]));
+    assert_eq!(hits(trie.fuzzy_search("rubicon", 0)), expected(&[("rubicon", 0)]));
+    assert_eq!(hits(trie.fuzzy_search("romulsu", 2)), expected(&[("romulus", 2)]));
+    assert!(trie.fuzzy_search("xyz", 2).is_empty());
+
+    // prefix mode for autocomplete
+    assert_eq!(hits(trie.fuzzy_prefix_search("rubcon", 1)), expected(&[("rubicon", 1), ("rubiconia", 1)]));
+    assert_eq!(hits(trie.fuzzy_prefix_search("roma", 0)), expected(&[("romane", 0), ("romanus", 0)]));
+    let values = trie.fuzzy_prefix_search("rubicom", 1).into_iter().map(|hit| hit.value).collect::<Vec<_>>();
+    assert_eq!(values, vec![None, Some(&1)]);
+
+    // the empty key is as far away as the query is long
+    trie.insert("", None);
+    assert_eq!(hits(trie.fuzzy_search("r", 1)), expected(&[("", 1)]));
+    assert_eq!(trie.fuzzy_prefix_search("r", 1).len(), trie.len());
+}
+
+#[test]
+fn test_pattern_matching() {
+    use ab_radix_trie::pattern::Pattern;
+    use ab_radix_trie::TrieError;
+    let mut trie: Trie<i32> = Trie::new();
+    for (i, key) in ["romane", "romanus", "romulus", "rubens", "ruber", "rubicon", "rubicundus", "rub*s", "ribbon"].iter().enumerate() {
+        trie.insert(key, Some(i as i32));
+    }
+    fn keys(trie: &Trie<i32>, pattern: &str) -> Vec<String> {
+        trie.matches(&pattern.parse().unwrap()).map(|(key, _)| key).collect()
+    }
+
+    assert_eq!(keys(&trie, "r?b??n"), vec!["ribbon"]);
+    assert_eq!(keys(&trie, "r?b???n"), vec!["rubicon"]);
+    assert_eq!(keys(&trie, "rub*s"), vec!["rub*s", "rubens", "rubicundus"]);
+    assert_eq!(keys(&trie, "rom[ae]*"), vec!["romane", "romanus"]);
+    assert_eq!(keys(&trie, "rom[!a]*"), vec!["romulus"]);
+    assert_eq!(keys(&trie, "r[o-z]b*"), vec!["rub*s", "rubens", "ruber", "rubicon", "rubicundus"]);
+    assert_eq!(keys(&trie, "rub\\*s"), vec!["rub*s"]);
+    assert_eq!(keys(&trie, "*us"), vec!["romanus", "romulus", "rubicundus"]);
+    assert_eq!(keys(&trie, "*"), trie.keys().collect::<Vec<_>>());
+    assert_eq!(keys(&trie, "ruber?"), Vec::<String>::new());
+    assert_eq!(keys(&trie, "ru"), Vec::<String>::new());
+
+    let values = trie.matches(&Pattern::new("rube?").unwrap()).map(|(_, value)| value).collect::<Vec<_>>();
+    assert_eq!(values, vec![Some(&4)]);
+
+    // the empty key only matches patterns which match nothing
+    trie.insert("", None);
+    assert_eq!(keys(&trie, ""), vec![""]);
+    assert_eq!(keys(&trie, "*").first().map(String::as_str), Some(""));
+
+    assert_eq!(Pattern::new("rom[ae"), Err(TrieError::InvalidPattern { position: 3 }));
+    assert_eq!(Pattern::new("rom\\"), Err(TrieError::InvalidPattern { position: 3 }));
+    let pattern = Pattern::new("[]a-]?").unwrap();
+    assert!(pattern.is_match("]x") && pattern.is_match("-x") && pattern.is_match("ax"));
+    assert!(!pattern.is_match("bx") && !pattern.is_match("a"));
+}
+
+#[test]
+fn test_regex_matching() {
+    use ab_radix_trie::regex::Regex;
+    use ab_radix_trie::TrieError;
+    let mut trie: Trie<usize> = Trie::new();
+    let identifiers = ["user_id", "user_name", "userId", "UserName", "_tmp1", "tmp2", "x", "x86_64", "a.b", "a-b"];
+    for (i, key) in identifiers.iter().enumerate() {
+        trie.insert(key, Some(i));
+    }
+    fn keys(trie: &Trie<usize>, regex: &str) -> Vec<String> {
+        trie.matches_regex(&regex.parse().unwrap()).map(|(key, _)| key).collect()
+    }
+    // every key must be matched in full
+    assert_eq!(keys(&trie, "user"), Vec::<String>::new());
+    assert_eq!(keys(&trie, "user.*"), vec!["userId", "user_id", "user_name"]);
+    assert_eq!(keys(&trie, "[a-z]+(_[a-z]+)*"), vec!["user_id", "user_name", "x"]);
+    assert_eq!(keys(&trie, "^[A-Za-z_]\\w*$"), vec!["UserName", "_tmp1", "tmp2", "userId", "user_id", "user_name", "x", "x86_64"]);
+    assert_eq!(keys(&trie, "[a-z]+\\d"), vec!["tmp2"]);
+    assert_eq!(keys(&trie, "(?:user|User)(Id|Name|_id)"), vec!["UserName", "userId", "user_id"]);
+    assert_eq!(keys(&trie, "x(86_64)?"), vec!["x", "x86_64"]);
+    assert_eq!(keys(&trie, "x\\d{2}_\\d{1,3}"), vec!["x86_64"]);
+    assert_eq!(keys(&trie, "a[.]b|a\\-b"), vec!["a-b", "a.b"]);
+    assert_eq!(keys(&trie, "a[^.]b"), vec!["a-b"]);
+    assert_eq!(keys(&trie, ".{4}"), vec!["tmp2"]);
+    assert_eq!(keys(&trie, ".{9,}"), vec!["user_name"]);
+
+    let values = trie.matches_regex(&Regex::new("_?tmp\\d").unwrap()).map(|(_, value)| value).collect::<Vec<_>>();
+    assert_eq!(values, vec![Some(&4), Some(&5)]);
+
+    // the empty key is matched by expressions which match nothing
+    trie.insert("", None);
+    assert_eq!(keys(&trie, "(x86)?"), vec![""]);
+    assert_eq!(keys(&trie, ".*").len(), trie.len());
+
+    let regex = Regex::new("(a|b)*c").unwrap();
+    assert!(regex.is_match("c") && regex.is_match("abbac") && !regex.is_match("abd"));
+    for (invalid, position) in [("(ab", 0), ("ab)", 2), ("*a", 0), ("a{2", 1), ("a{3,2}", 1), ("[ab", 0), ("a\\", 1), ("\\q", 0), ("a^", 1), ("[z-a]", 0)] {
+        assert_eq!(Regex::new(invalid), Err(TrieError::InvalidRegex { position }), "{}", invalid);
+    }
+    // nested repetitions multiply, the compiled expression is bounded as a whole
+    assert!(Regex::new("(\\w{1,1000}_){4}").is_ok());
+    for (invalid, position) in [("a{1001}", 1), ("((a{1000}){1000}){10}", 10), ("(a{100}b{100}){50}", 14)] {
+        assert_eq!(Regex::new(invalid), Err(TrieError::InvalidRegex { position }), "{}", invalid);
+    }
+    assert_eq!(Regex::new(&"a".repeat(10_000)), Err(TrieError::InvalidRegex { position: 0 }));
+    // groups and repetitions nest a bounded number of levels, parsing and compiling recurse on them
+    let nested = |depth: usize, inner: &str| format!("{}{}{}", "(".repeat(depth), inner, ")".repeat(depth));
+    assert!(Regex::new(&nested(256, "a")).unwrap().is_match("a"));
+    assert!(Regex::new(&format!("a{}", "*".repeat(256))).is_ok());
+    for (invalid, position) in [(nested(257, "a"), 256), (nested(50_000, "a"), 256), (format!("a{}", "?".repeat(50_000)), 257),
+                                (nested(200, &format!("a{}", "?".repeat(57))), 0), (format!("{}*", nested(256, "a")), 256 * 2 + 1)] {
+        assert_eq!(Regex::new(&invalid), Err(TrieError::InvalidRegex { position }));
+    }
+}
+
+#[test]
+fn test_generic_keys() {
+    // binary keys
+    let mut bytes: Trie<u32, [u8]> = Trie::new();
+    bytes.insert(&[0x0a, 0x00, 0x00, 0x01], Some(1));
+    bytes.insert(&[0x0a, 0x00, 0x01], Some(2));
+    bytes.insert(&[0x0a, 0xff], Some(3));
+    bytes.insert(&[0x0a], None);
+    bytes.insert(&[], Some(0));
+    assert_eq!(bytes.len(), 5);
+    assert_eq!(bytes.get(&[0x0a, 0x00, 0x01]), Some(&2));
+    assert!(bytes.contains_key(&[0x0a]));
+    assert!(!bytes.contains_key(&[0x0a, 0x00]));
+    assert_eq!(bytes.longest_prefix_match(&[0x0a, 0x00, 0x01, 0x07]), Some((&[0x0a, 0x00, 0x01][..], Some(&2))));
+    let keys = bytes.keys().collect::<Vec<_>>();
+    assert_eq!(keys, vec![vec![], vec![0x0a], vec![0x0a, 0x00, 0x00, 0x01], vec![0x0a, 0x00, 0x01], vec![0x0a, 0xff]]);
+    assert_eq!(bytes.remove(&[0x0a, 0x00, 0x00, 0x01]), Some(1));
+    assert_eq!(bytes.iter_prefix(&[0x0a, 0x00]).map(|(key, _)| key).collect::<Vec<_>>(), vec![vec![0x0a, 0x00, 0x01]]);
+
+    // token ids
+    let mut tokens: Trie<&str, [u32]> = Trie::new();
+    tokens.insert(&[464, 3290, 318], Some("the cat is"));
+    tokens.insert(&[464, 3290, 3332], Some("the cat sat"));
+    tokens.insert(&[464, 3332], Some("the sat"));
+    *tokens.entry(&[464]).or_insert("the") = "The";
+    assert_eq!(tokens.get(&[464]), Some(&"The"));
+    let best = tokens.top_k(&[464, 3290], 1, ByKeyLength);
+    assert_eq!(best, vec![(vec![464, 3290, 318], Some(&"the cat is"))]);
+    assert_eq!(tokens.stats().node_count, 5);
+
+    // word sequences
+    let mut phrases: Trie<usize, [&str]> = Trie::new();
+    for (i, phrase) in ["new york city", "new york", "new jersey", "york"].iter().enumerate() {
+        let words = phrase.split(' ').collect::<Vec<_>>();
+        phrases.insert(&words, Some(i));
+    }
+    let prefixes = phrases.prefixes_of(&["new", "york", "city", "hall"]).into_iter().map(|(key, _)| key.len()).collect::<Vec<_>>();
+    assert_eq!(prefixes, vec![2, 3]);
+    let keys = phrases.iter_prefix(&["new"]).map(|(key, _)| key.join(" ")).collect::<Vec<_>>();
+    assert_eq!(keys, vec!["new jersey", "new york", "new york city"]);
+}
+
+#[test]
+fn test_wide_fan_out() {
+    // enough children under the root and under "x" to outgrow a small node
+    let mut trie: Trie<u32> = Trie::new();
+    let chars = ('0'..='9').chain('A'..='Z').chain('a'..='z').chain('α'..='ω').collect::<Vec<_>>();
+    for (i, c) in chars.iter().rev().enumerate() {
+        trie.insert(&c.to_string(), Some(i as u32));
+        trie.insert(&format!("x{c}"), Some(i as u32));
+    }
+    assert_eq!(trie.len(), 2 * chars.len());
+    let mut expected = chars.iter().map(|c| c.to_string()).collect::<Vec<_>>();
+    let at = expected.iter().position(|key| key == "x").unwrap();
+    expected.splice(at + 1..at + 1, chars.iter().map(|c| format!("x{c}")));
+    assert_eq!(trie.keys().collect::<Vec<_>>(), expected);
+    assert_eq!(trie.get("xQ"), trie.get("Q"));
+
+    for c in chars.iter().filter(|c| c.is_ascii_lowercase()) {
+        assert!(trie.remove(&format!("x{c}")).is_some());
+    }
+    assert_eq!(trie.iter_prefix("x").count(), 1 + chars.len() - 26);
+    assert_eq!(trie.keys().last(), Some("ω".to_string()));
+
+    #[cfg(feature = "serde")]
+    {
+        let json = serde_json::to_string(&trie).unwrap();
+        let copy: Trie<u32> = serde_json::from_str(&json).unwrap();
+        assert_eq!(copy.iter().collect::<Vec<_>>(), trie.iter().collect::<Vec<_>>());
+        // children are serialized as a map keyed by their first char
+        assert!(json.contains(r#""children":{"0":{"text":"0""#));
+    }
+}
+
+#[test]
+fn test_suffixes_of_multibyte_keys() {
+    let mut trie: Trie<i32> = Trie::new();
+    trie.insert("🤡é🤡", Some(1));
+    trie.insert("🤡é🤡ab", Some(2));
+    trie.insert("🤡éx", Some(3));
+    trie.insert("🤡 é", Some(4));
+
+    let root = trie.suffix_tree("🤡é🤡a").unwrap();
+    assert_eq!(root.value(), Some(&2));
+    let results = trie.get_suffixes_values("🤡é").unwrap();
+    let suffixes = results.into_iter().map(|x| (x.key, *x.val)).collect::<Vec<_>>();
+    assert_eq!(suffixes, vec![("x".to_string(), Some(3)), ("🤡".to_string(), Some(1)), ("🤡ab".to_string(), Some(2))]);
+    let results = trie.get_suffixes_values("🤡é🤡a").unwrap();
+    assert_eq!(results.into_iter().map(|x| x.key).collect::<Vec<_>>(), vec!["b"]);
+    #[cfg(feature = "std")]
+    assert_eq!(trie.get_string_suffixes("🤡é🤡"), HashSet::from(["".to_string(), "ab".to_string()]));
+
+    let options = ab_radix_trie::MatchingOptions::ignoring_white_space();
+    let results = trie.get_suffixes_with_matching_options("🤡 é🤡", &options).unwrap();
+    assert!(results.iter().any(|x| x.key == "ab"));
+}
+
+#[test]
+fn test_frozen() {
+    let mut trie: Trie<i32> = Trie::new();
+    for (i, key) in ["romane", "romanus", "romulus", "rubens", "ruber", "rubicon", "rubicundus", "🤡é🤡", "🤡éx", "rom anus"].iter().enumerate() {
+        trie.insert(key, Some(i as i32));
+    }
+    trie.insert("", None);
+    trie.insert("rub", None);
+    let expected = trie.iter().map(|(key, value)| (key, value.copied())).collect::<Vec<_>>();
+    let options = ab_radix_trie::MatchingOptions::ignoring_white_space();
+    let matching = trie.get_keys_with_matching_options("roma", &options).into_iter().map(|x| (x.key, *x.val)).collect::<Vec<_>>();
+
+    let frozen = trie.freeze();
+    assert_eq!(frozen.len(), expected.len());
+    assert_eq!(frozen.iter().map(|(key, value)| (key, value.copied())).collect::<Vec<_>>(), expected);
+    assert_eq!(frozen.get("romulus"), Some(&2));
+    assert_eq!(frozen.get("🤡éx"), Some(&8));
+    assert_eq!(frozen.get("rub"), None);
+    assert!(frozen.contains_key("rub"));
+    assert!(frozen.contains_key(""));
+    assert!(!frozen.contains_key("rubi"));
+    assert!(!frozen.contains_key("romulusx"));
+
+    let keys = frozen.iter_prefix("rubi").map(|(key, _)| key).collect::<Vec<_>>();
+    assert_eq!(keys, vec!["rubicon", "rubicundus"]);
+    assert_eq!(frozen.iter_prefix("rube").count(), 2);
+    assert_eq!(frozen.iter_prefix("rubx").count(), 0);
+    assert_eq!(frozen.iter_prefix("").count(), expected.len());
+    assert_eq!(frozen.longest_prefix_match("rubicons"), Some(("rubicon", Some(&5))));
+    assert_eq!(frozen.prefixes_of("rubens").into_iter().map(|(key, _)| key).collect::<Vec<_>>(), vec!["", "rub", "rubens"]);
+
+    let frozen_matching = frozen.get_keys_with_matching_options("roma", &options).into_iter().map(|x| (x.key, *x.val)).collect::<Vec<_>>();
+    assert_eq!(frozen_matching, matching);
+    assert_eq!(frozen_matching.len(), 3);
+}
+
+#[test]
+fn test_mapped_image() {
+    use ab_radix_trie::image::MappedTrie;
+    use ab_radix_trie::TrieError;
+
+    let mut trie: Trie<String> = Trie::new();
+    for key in ["romane", "romanus", "romulus", "rubens", "ruber", "rubicon", "rubicundus", "🤡é🤡", "🤡éx"] {
+        trie.insert(key, Some(key.to_uppercase()));
+    }
+    trie.insert("rub", None);
+    let expected = trie.iter().map(|(key, value)| (key, value.map(|value| value.as_bytes().to_vec()))).collect::<Vec<_>>();
+    let image = trie.freeze().to_image(|value, out| out.extend_from_slice(value.as_bytes()));
+
+    let mapped = MappedTrie::new(&image).unwrap();
+    assert_eq!(mapped.len(), expected.len());
+    assert_eq!(mapped.iter().map(|(key, value)| (key, value.map(<[u8]>::to_vec))).collect::<Vec<_>>(), expected);
+    assert_eq!(mapped.get("🤡éx"), Some("🤡ÉX".as_bytes()));
+    assert_eq!(mapped.get("rub"), None);
+    assert!(mapped.contains_key("rub"));
+    assert!(!mapped.contains_key("rubi"));
+    assert_eq!(mapped.iter_prefix("rubi").map(|(key, _)| key).collect::<Vec<_>>(), vec!["rubicon", "rubicundus"]);
+    assert_eq!(mapped.longest_prefix_match("rubicons"), Some(("rubicon", Some("RUBICON".as_bytes()))));
+
+    let mut damaged = image.clone();
+    damaged[40] ^= 1;
+    assert_eq!(MappedTrie::new(&damaged).unwrap_err(), TrieError::Corrupt { reason: "checksum mismatch" });
+    let mut newer = image.clone();
+    newer[8] = 2;
+    assert_eq!(MappedTrie::new(&newer).unwrap_err(), TrieError::UnsupportedVersion { version: 2 });
+    assert!(MappedTrie::new(&image[..image.len() - 1]).is_err());
+    assert!(MappedTrie::new(b"not an image").is_err());
+
+    // damages the image by hand and writes a checksum to match, the node records start at 32 and take 24 bytes
+    fn rewrite(image: &[u8], damage: impl FnOnce(&mut [u8])) -> Vec<u8> {
+        let mut image = image.to_vec();
+        damage(&mut image);
+        image[28..32].fill(0);
+        let checksum = image.iter().fold(u32::MAX, |crc, byte| {
+            (0..8).fold(crc ^ *byte as u32, |crc, _| if crc & 1 == 1 { (crc >> 1) ^ 0xedb8_8320 } else { crc >> 1 })
+        });
+        image[28..32].copy_from_slice(&(!checksum).to_le_bytes());
+        image
+    }
+    fn set(image: &mut [u8], node: usize, field: usize, n: u32) {
+        let at = 32 + node * 24 + field;
+        image[at..at + 4].copy_from_slice(&n.to_le_bytes());
+    }
+
+    // the records of the first two children of the root swapped
+    let unsorted = rewrite(&image, |image| {
+        let (first, second) = image[32 + 24..32 + 3 * 24].split_at_mut(24);
+        first.swap_with_slice(second);
+    });
+    assert_eq!(MappedTrie::new(&unsorted).unwrap_err(), TrieError::Corrupt { reason: "children are not sorted" });
+    // a label whose end does not fit in a u32
+    let overflowing = rewrite(&image, |image| set(image, 1, 4, u32::MAX - 1));
+    assert_eq!(MappedTrie::new(&overflowing).unwrap_err(), TrieError::Corrupt { reason: "label out of bounds" });
+
+    // the root of "a" and "b" has no children left and each of them is its own child, the counts still add up
+    let mut pair: Trie<String> = Trie::new();
+    pair.insert("a", Some("A".to_string()));
+    pair.insert("b", Some("B".to_string()));
+    let image = pair.freeze().to_image(|value, out| out.extend_from_slice(value.as_bytes()));
+    assert!(MappedTrie::new(&image).is_ok());
+    let cyclic = rewrite(&image, |image| {
+        set(image, 0, 16, 0);
+        for node in [1, 2] {
+            set(image, node, 12, node as u32);
+            set(image, node, 16, 1);
+        }
+    });
+    assert_eq!(MappedTrie::new(&cyclic).unwrap_err(), TrieError::Corrupt { reason: "unreachable nodes or values" });
+
+    let empty: Trie<String> = Trie::new();
+    let image = empty.freeze().to_image(|value, out| out.extend_from_slice(value.as_bytes()));
+    assert!(MappedTrie::new(&image).unwrap().is_empty());
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_binary_round_trip() {
+    use std::io::ErrorKind;
+    use ab_radix_trie::TrieError;
+
+    let mut trie: Trie<String> = Trie::new();
+    for key in ["romane", "romanus", "romulus", "rubens", "ruber", "rubicon", "rubicundus", "🤡é🤡", "🤡éx"] {
+        trie.insert(key, Some(key.to_uppercase()));
+    }
+    trie.insert("rub", None);
+    trie.insert("", Some("root".to_string()));
+    trie.remove("romulus");
+    let mut bytes = Vec::new();
+    trie.write_to(&mut bytes).unwrap();
+
+    let copy: Trie<String> = Trie::read_from(bytes.as_slice()).unwrap();
+    assert_eq!(copy.iter().collect::<Vec<_>>(), trie.iter().collect::<Vec<_>>());
+    assert_eq!(copy.stats(), trie.stats());
+    assert_eq!(copy.get(""), Some(&"root".to_string()));
+    // much smaller than the nested json
+    #[cfg(feature = "serde")]
+    assert!(bytes.len() * 3 < serde_json::to_string(&trie).unwrap().len());
+
+    let reason = |bytes: &[u8]| {
+        let error = Trie::<String>::read_from(bytes).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+        error.into_inner().unwrap().downcast::<TrieError>().map(|error| *error).unwrap()
+    };
+    let mut damaged = bytes.clone();
+    let at = bytes.windows(5).position(|window| window == b"undus").unwrap();
+    damaged[at + 4] = b'S';
+    assert_eq!(reason(&damaged), TrieError::Corrupt { reason: "checksum mismatch" });
+    let mut newer = bytes.clone();
+    newer[8] = 2;
+    assert_eq!(reason(&newer), TrieError::UnsupportedVersion { version: 2 });
+    assert_eq!(reason(b"{\"children\":{}}"), TrieError::Corrupt { reason: "not a trie stream" });
+    let truncated = Trie::<String>::read_from(&bytes[..bytes.len() - 1]).unwrap_err();
+    assert_eq!(truncated.kind(), ErrorKind::UnexpectedEof);
+
+    // a value which does not decode
+    let mut numbers: Trie<u32> = Trie::new();
+    numbers.insert("a", Some(1));
+    let mut bytes = Vec::new();
+    numbers.write_to(&mut bytes).unwrap();
+    assert!(Trie::<u64>::read_from(bytes.as_slice()).is_err());
+    assert_eq!(Trie::<u32>::read_from(bytes.as_slice()).unwrap().get("a"), Some(&1));
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_flat_serde() {
+    #[derive(serde::Serialize, serde::Deserialize)]
+    struct Flat {
+        #[serde(with = "ab_radix_trie::flat")]
+        trie: Trie<i32>,
+    }
+
+    let mut trie: Trie<i32> = Trie::new();
+    for (i, key) in ["ruber", "rubens", "🤡é", "", "a \"quoted\" key"].iter().enumerate() {
+        trie.insert(key, Some(i as i32));
+    }
+    trie.insert("rub", None);
+    let json = serde_json::to_string(&Flat { trie: trie.clone() }).unwrap();
+    assert_eq!(json, r#"{"trie":{"":3,"a \"quoted\" key":4,"rub":null,"rubens":1,"ruber":0,"🤡é":2}}"#);
+    let copy: Flat = serde_json::from_str(&json).unwrap();
+    assert_eq!(copy.trie.iter().collect::<Vec<_>>(), trie.iter().collect::<Vec<_>>());
+    assert_eq!(copy.trie.stats(), trie.stats());
+
+    // any map will do, in any order
+    let copy: Flat = serde_json::from_str(r#"{"trie":{"rubens":1,"ruber":2,"rubens":3}}"#).unwrap();
+    assert_eq!(copy.trie.len(), 2);
+    assert_eq!(copy.trie.get("rubens"), Some(&3));
+    assert!(serde_json::from_str::<Flat>(r#"{"trie":["ruber"]}"#).is_err());
+}
+
+#[test]
+fn test_validate() {
+    let mut trie: Trie<i32> = Trie::new();
+    let keys = ["romane", "romanus", "romulus", "rubens", "ruber", "rubicon", "rubicundus", "🤡é🤡", "🤡éx", "🤡", ""];
+    for (i, key) in keys.iter().enumerate() {
+        trie.insert(key, Some(i as i32));
+        assert_eq!(trie.validate(), Ok(()));
+    }
+    for key in keys.iter().step_by(2).chain(["rub", "missing"].iter()) {
+        trie.remove(key);
+        assert_eq!(trie.validate(), Ok(()), "after removing {key}");
+    }
+
+    #[cfg(feature = "serde")]
+    {
+        use ab_radix_trie::InvariantViolation;
+        // the counters and weights are recomputed when deserializing, but the structure is taken as it is
+        let json = r#"{"children":{"r":{"text":"rub","terminal":false,"value":null,"weight":3,"children":{
+            "e":{"text":"ens","terminal":true,"value":1,"children":{}}}}}}"#;
+        let trie: Trie<i32> = serde_json::from_str(json).unwrap();
+        let violations = trie.validate().unwrap_err();
+        assert_eq!(violations, vec![InvariantViolation::Uncompressed { key: "rub".to_string() }]);
+    }
+}
+
+#[test]
+fn test_suffixes_of_a_prefix_ending_within_a_node() {
+    let suffixes = |trie: &Trie<i32>, prefix: &str| trie.get_suffixes_values(prefix).unwrap().into_iter().map(|x| x.key).collect::<Vec<_>>();
+    // the part of the node covered by the prefix used to be guessed from their overlap, which gave "ab" here
+    let mut trie: Trie<i32> = Trie::new();
+    trie.insert("aab", Some(1));
+    trie.insert("babb", Some(2));
+    assert_eq!(suffixes(&trie, "aa"), vec!["b"]);
+    #[cfg(feature = "std")]
+    assert_eq!(trie.get_string_suffixes("aa"), HashSet::from(["b".to_string()]));
+
+    // and "aa" here
+    let mut trie: Trie<i32> = Trie::new();
+    trie.insert("aaa", Some(1));
+    trie.insert("baaa", Some(2));
+    assert_eq!(suffixes(&trie, "aaa"), vec![""]);
+
+    // the prefix ends inside a node whose next char is also the first char of one of its children,
+    // the walk used to go on into that child and found nothing
+    let mut trie: Trie<i32> = Trie::new();
+    trie.insert("abbc", Some(1));
+    trie.insert("abx", Some(2));
+    assert_eq!(suffixes(&trie, "a"), vec!["bbc", "bx"]);
+}
+
+#[test]
+#[cfg(feature = "serde")]
+fn test_deserialize_json_without_counters() {
+    // written before the key count was kept, the node and char counts are left out too
+    let json = r#"{"children":{"r":{"text":"r","terminal":false,"value":null,"visit_count":0,"weight":26,"children":{
+        "o":{"text":"om","terminal":false,"value":null,"visit_count":0,"weight":10,"children":{
+            "a":{"text":"anus","terminal":true,"value":null,"visit_count":0,"weight":4,"children":{}},
+            "u":{"text":"ulus","terminal":true,"value":10,"visit_count":0,"weight":4,"children":{}}}},
+        "u":{"text":"ub","terminal":false,"value":null,"visit_count":0,"weight":15,"children":{
+            "e":{"text":"e","terminal":false,"value":null,"visit_count":0,"weight":4,"children":{
+                "n":{"text":"ns","terminal":true,"value":null,"visit_count":0,"weight":2,"children":{}},
+                "r":{"text":"r","terminal":true,"value":null,"visit_count":0,"weight":1,"children":{}}}},
+            "i":{"text":"ic","terminal":false,"value":null,"visit_count":0,"weight":9,"children":{
+                "o":{"text":"on","terminal":true,"value":null,"visit_count":0,"weight":2,"children":{}},
+                "u":{"text":"undus","terminal":true,"value":null,"visit_count":0,"weight":5,"children":{}}}}}}}}}}"#;
+    let mut trie: Trie<i32> = serde_json::from_str(json).unwrap();
+    assert_eq!(trie.validate(), Ok(()));
+    assert_eq!(trie.len(), 6);
+    assert_eq!((trie.stats().node_count, trie.stats().char_count), (11, 26));
+    assert_eq!(trie.remove("romanus"), None);
+    assert_eq!(trie.remove("romulus"), Some(10));
+    assert_eq!(trie.len(), 4);
+    assert_eq!(trie.validate(), Ok(()));
+
+    // counters which are present but wrong are not trusted either
+    let json = json.replacen('{', r#"{"node_count":1,"char_count":2,"key_count":3,"#, 1);
+    let trie: Trie<i32> = serde_json::from_str(&json).unwrap();
+    assert_eq!(trie.validate(), Ok(()));
+}
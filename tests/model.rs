@@ -0,0 +1,241 @@
+//! model based tests: random sequences of operations applied to a trie and to a `BTreeMap`, whose results must agree
+//!
+//! the trie is validated after every step. the sequences are made up by a seeded xorshift so failures
+//! reproduce, `MODEL_SEED` runs a single sequence and `MODEL_CASES` sets how many are run.
+use std::collections::BTreeMap;
+use ab_radix_trie::Trie;
+use ab_radix_trie::rank::ByValue;
+
+/// few symbols (several of them multibyte) so the keys share prefixes and split nodes inside chars' neighbours
+const SYMBOLS: [&str; 8] = ["a", "b", "ab", "é", "ß", "🤡", "e\u{301}", "\u{10ffff}"];
+
+type Model = BTreeMap<String, Option<u8>>;
+
+#[derive(Debug)]
+enum Op {
+    Insert(String, Option<u8>),
+    InsertIfAbsent(String, Option<u8>),
+    Remove(String),
+    Get(String),
+    GetMut(String, u8),
+    TopK(String, usize),
+    PrefixesOf(String),
+    IterPrefix(String),
+    SuffixesValues(String),
+    Iter,
+    Clear,
+}
+
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: u64) -> u64 {
+        self.next() % n
+    }
+
+    fn key(&mut self) -> String {
+        (0..self.below(6)).map(|_| SYMBOLS[self.below(SYMBOLS.len() as u64) as usize]).collect()
+    }
+
+    /// a key which is in the model half of the time, so removals and lookups hit
+    fn known_key(&mut self, model: &Model) -> String {
+        match model.keys().nth(self.below(2 * model.len() as u64 + 1) as usize) {
+            Some(key) => key.clone(),
+            None => self.key(),
+        }
+    }
+
+    fn value(&mut self) -> Option<u8> {
+        let value = self.next() as u8;
+        (!value.is_multiple_of(4)).then_some(value)
+    }
+
+    fn op(&mut self, model: &Model) -> Op {
+        match self.below(100) {
+            0..=34 => Op::Insert(self.key(), self.value()),
+            35..=39 => Op::InsertIfAbsent(self.known_key(model), self.value()),
+            40..=60 => Op::Remove(self.known_key(model)),
+            61..=64 => Op::GetMut(self.known_key(model), self.next() as u8),
+            65..=71 => Op::Get(self.known_key(model)),
+            72..=74 => {
+                let key = self.known_key(model);
+                let cut = key.char_indices().map(|(i, _)| i).nth(self.below(3) as usize).unwrap_or(key.len());
+                Op::TopK(key[..cut].to_string(), self.below(4) as usize)
+            }
+            75..=79 => {
+                let mut input = self.known_key(model);
+                input.push_str(&self.key());
+                Op::PrefixesOf(input)
+            }
+            80..=87 => {
+                let key = self.known_key(model);
+                let cut = key.char_indices().map(|(i, _)| i).nth(self.below(4) as usize).unwrap_or(key.len());
+                Op::IterPrefix(key[..cut].to_string())
+            }
+            88..=95 => Op::SuffixesValues(self.key()),
+            96..=98 => Op::Iter,
+            _ => Op::Clear,
+        }
+    }
+}
+
+fn with_prefix<'a>(model: &'a Model, prefix: &'a str) -> impl Iterator<Item = (&'a String, &'a Option<u8>)> {
+    model.range(prefix.to_string()..).take_while(move |(key, _)| key.starts_with(prefix))
+}
+
+fn apply(trie: &mut Trie<u8>, model: &mut Model, op: &Op) {
+    match op {
+        Op::Insert(key, value) => {
+            let previous = model.insert(key.clone(), *value).flatten();
+            assert_eq!(trie.insert(key, *value), previous);
+        }
+        Op::InsertIfAbsent(key, value) => {
+            let slot = model.entry(key.clone()).or_insert(None);
+            if slot.is_none() {
+                *slot = *value;
+            }
+            trie.insert_if_absent(key, *value);
+        }
+        Op::Remove(key) => assert_eq!(trie.remove(key), model.remove(key).flatten()),
+        Op::Get(key) => {
+            assert_eq!(trie.get(key), model.get(key).and_then(Option::as_ref));
+            assert_eq!(trie.contains_key(key), model.contains_key(key));
+        }
+        Op::GetMut(key, value) => {
+            let expected = model.get_mut(key).and_then(Option::as_mut);
+            let found = trie.get_mut(key);
+            assert_eq!(found.as_deref(), expected.as_deref());
+            if let (Some(found), Some(expected)) = (found, expected) {
+                *found = *value;
+                *expected = *value;
+            }
+        }
+        Op::TopK(prefix, k) => {
+            let mut expected = with_prefix(model, prefix)
+                .filter_map(|(key, value)| value.as_ref().map(|value| (key.clone(), Some(value))))
+                .collect::<Vec<_>>();
+            // best first, the ties in key order
+            expected.sort_by_key(|(_, value)| std::cmp::Reverse(*value));
+            expected.truncate(*k);
+            assert_eq!(trie.top_k(prefix, *k, ByValue), expected);
+        }
+        Op::PrefixesOf(input) => {
+            let expected = input.char_indices().map(|(i, _)| i).chain([input.len()])
+                .filter_map(|i| model.get_key_value(&input[..i]))
+                .map(|(key, value)| (key.as_str(), value.as_ref()))
+                .collect::<Vec<_>>();
+            assert_eq!(trie.longest_prefix_match(input), expected.last().copied());
+            assert_eq!(trie.prefixes_of(input), expected);
+        }
+        Op::IterPrefix(prefix) => {
+            let expected = with_prefix(model, prefix).map(|(key, value)| (key.clone(), value.as_ref())).collect::<Vec<_>>();
+            assert_eq!(trie.iter_prefix(prefix).collect::<Vec<_>>(), expected);
+        }
+        Op::SuffixesValues(prefix) => {
+            let expected = with_prefix(model, prefix).map(|(key, value)| (key[prefix.len()..].to_string(), *value)).collect::<Vec<_>>();
+            let suffixes = trie.get_suffixes_values(prefix)
+                .map(|entries| entries.into_iter().map(|entry| (entry.key, *entry.val)).collect::<Vec<_>>());
+            if prefix.is_empty() || expected.is_empty() {
+                assert!(suffixes.is_none_or(|suffixes| suffixes.is_empty()));
+            } else {
+                assert_eq!(suffixes, Some(expected));
+            }
+        }
+        Op::Iter => {
+            let expected = model.iter().map(|(key, value)| (key.clone(), value.as_ref())).collect::<Vec<_>>();
+            assert_eq!(trie.iter().collect::<Vec<_>>(), expected);
+        }
+        Op::Clear => {
+            trie.clear();
+            model.clear();
+        }
+    }
+}
+
+fn run(seed: u64, steps: usize) {
+    let mut rng = Rng(seed | 1);
+    let mut trie: Trie<u8> = Trie::new();
+    // half of the sequences count the visits, so the visit bounds are validated as the nodes change
+    #[cfg(feature = "std")]
+    trie.track_visits(seed.is_multiple_of(2));
+    // and most of them keep the largest values, which are validated the same way and prune `top_k`
+    trie.track_max_values(!seed.is_multiple_of(3));
+    let mut model = Model::new();
+    let mut ops = Vec::new();
+    for _ in 0..steps {
+        ops.push(rng.op(&model));
+        let op = ops.last().unwrap();
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            apply(&mut trie, &mut model, op);
+            trie.merge_visits();
+            assert_eq!(trie.validate(), Ok(()));
+            assert_eq!(trie.len(), model.len());
+        }));
+        if let Err(panic) = result {
+            eprintln!("seed {seed} failed at step {}, the operations were:", ops.len() - 1);
+            for op in &ops {
+                eprintln!("  {op:?}");
+            }
+            std::panic::resume_unwind(panic)
+        }
+    }
+
+    // the other representations of the final trie
+    let expected = model.iter().map(|(key, value)| (key.clone(), *value)).collect::<Vec<_>>();
+    let frozen = trie.clone().freeze();
+    assert_eq!(frozen.iter().map(|(key, value)| (key, value.copied())).collect::<Vec<_>>(), expected);
+    #[cfg(feature = "std")]
+    {
+        let mut bytes = Vec::new();
+        trie.write_to(&mut bytes).unwrap();
+        let copy: Trie<u8> = Trie::read_from(bytes.as_slice()).unwrap();
+        assert_eq!(copy.stats(), trie.stats());
+        assert_eq!(copy.into_iter().collect::<Vec<_>>(), expected);
+    }
+}
+
+fn env(name: &str) -> Option<u64> {
+    std::env::var(name).ok().map(|value| value.parse().expect("a number"))
+}
+
+#[test]
+fn model_random_operations() {
+    if let Some(seed) = env("MODEL_SEED") {
+        return run(seed, 400)
+    }
+    for case in 0..env("MODEL_CASES").unwrap_or(300) {
+        run(0x9e3779b97f4a7c15_u64.wrapping_mul(case + 1), 400);
+    }
+}
+
+#[test]
+fn model_remove_everything() {
+    // grows a trie with every short key then takes it apart in a scrambled order, merging nodes all the way
+    let mut keys = vec![String::new()];
+    for _ in 0..3 {
+        keys = keys.iter().flat_map(|key| SYMBOLS.iter().map(move |symbol| format!("{key}{symbol}"))).chain(keys.clone()).collect();
+    }
+    keys.sort();
+    keys.dedup();
+    let mut trie: Trie<u8> = Trie::new();
+    let mut model = Model::new();
+    for (i, key) in keys.iter().enumerate() {
+        apply(&mut trie, &mut model, &Op::Insert(key.clone(), Some(i as u8)));
+    }
+    assert_eq!(trie.validate(), Ok(()));
+    let mut rng = Rng(7);
+    while !keys.is_empty() {
+        let key = keys.swap_remove(rng.below(keys.len() as u64) as usize);
+        apply(&mut trie, &mut model, &Op::Remove(key));
+        assert_eq!(trie.validate(), Ok(()));
+    }
+    assert!(trie.is_empty());
+    assert_eq!(trie.stats().node_count, 0);
+}